@@ -1,19 +1,56 @@
-use std::cmp::{max, Ordering};
-use std::collections::HashMap;
-use std::io::{self, Write};
+use std::cmp::{max, min, Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
 
-use chrono::{Datelike, Utc};
+use chrono::{Datelike, Duration, Local, NaiveDate, Utc};
 
+use config::{Config, JellyfinConfig};
+use digest::Digest;
 use errors::*;
-use tvmaze_api::{Episode, SearchResult, Show, Status, TvMazeApi};
-use user_data::UserData;
+use export::{Export, TraktHistory};
+use import::{ImportedEpisode, ImportedShow};
+use jellyfin::Jellyfin;
+use library;
+use plan;
+use rand::{self, Rng};
+use smtp::{self, Mail};
+use stats::Stats;
+use tvmaze_api::{Episode, SearchResult, Show, Status, TvMazeApi, ViewingState};
+use user_data::{self, MergeConflict, SeasonProgress, UserData, WatchedEpisode};
+
+/// Formats a runtime given in minutes, e.g. "5h 20m".
+pub fn format_runtime(minutes: usize) -> String {
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
 
 #[derive(PartialEq)]
-enum HorizontalSeparator {
+pub enum HorizontalSeparator {
     Season,
     Week,
 }
 
+/// Order of the shows in the "next up" list.
+pub enum NextUpOrder {
+    LastActivity,
+    AirDate,
+    Priority,
+}
+
+/// Format of the exported user data.
+pub enum ExportFormat {
+    /// All user data, see `docs/export-format.md`
+    Bingers,
+    /// Watch history as body of Trakt's history sync
+    Trakt,
+}
+
 pub struct App {
     api: TvMazeApi,
     user_data: UserData,
@@ -29,6 +66,16 @@ impl App {
         })
     }
 
+    /// App working on the given user data instead of the stored one
+    #[cfg(test)]
+    pub fn with_user_data(user_data: UserData) -> Result<Self> {
+        Ok(Self {
+            api: TvMazeApi::new(false)?,
+            user_data,
+            verbose: false,
+        })
+    }
+
     fn select_show_to_add(&self, search_results: &[SearchResult]) -> Result<Option<Show>> {
         // TODO: make language user preference
         for result in search_results
@@ -116,42 +163,98 @@ impl App {
         matched_shows
     }
 
-    // TODO: refactor such that this actually works with the borrow checker
-    //
-    // fn resolve_show(&mut self, show: &str) -> Result<Option<Show>> {
-    //     let search_results = self.api
-    //         .search_shows(show)
-    //         .chain_err(|| format!("Unable to search for show [\"{}\"]", show))?;
-
-    //     let selected_show = {
-    //         let matched_shows = self.match_with_subscribed_shows(&search_results);
-
-    //         if matched_shows.is_empty() {
-    //             println!("No matching show found.");
-    //             return Ok(None);
-    //         }
-
-    //         if matched_shows.len() > 1 {
-    //             match self.select_show(&matched_shows)? {
-    //                 Some(show) => show,
-    //                 None => {
-    //                     println!("No matching show found.");
-    //                     return Ok(None);
-    //                 }
-    //             }
-    //         } else {
-    //             matched_shows[0]
-    //         }
-    //     };
-
-    //     Ok(Some(*selected_show))
-    // }
+    /// Searches for the given show among the subscribed shows.
+    ///
+    /// Asks the user to select a show if there are several matches. Returns a copy of the
+    /// subscribed show, so that user data can be modified afterwards.
+    fn resolve_show(&mut self, show: &str) -> Result<Option<Show>> {
+        let search_results = self
+            .api
+            .search_shows(show)
+            .chain_err(|| format!("Unable to search for show [\"{}\"]", show))?;
+
+        let show_id = {
+            let matched_shows = self.match_with_subscribed_shows(&search_results);
+
+            if matched_shows.is_empty() {
+                println!("No matching show found.");
+                return Ok(None);
+            }
+
+            if matched_shows.len() > 1 {
+                match self.select_show(&matched_shows)? {
+                    Some(show) => show.id,
+                    None => {
+                        println!("No matching show found.");
+                        return Ok(None);
+                    }
+                }
+            } else {
+                matched_shows[0].id
+            }
+        };
+
+        if self.verbose {
+            println!();
+        }
+
+        Ok(self
+            .user_data
+            .subscribed_shows()
+            .iter()
+            .find(|show| show.id == show_id)
+            .cloned())
+    }
 
     fn print_episode_list_as_table<T: AsRef<Episode>>(
         episodes: &[T],
         separator: &HorizontalSeparator,
         show_names: Option<&HashMap<usize, &str>>,
+        local_files: Option<&BTreeMap<usize, PathBuf>>,
     ) {
+        print!(
+            "{}",
+            App::format_episode_list_as_table(episodes, separator, show_names, local_files)
+        );
+    }
+
+    /// Prints a progress bar for each season: '#' for watched, '-' for aired but unwatched
+    /// and ' ' for upcoming episodes.
+    fn print_season_progress(show: &Show, seasons: &[SeasonProgress]) {
+        const WIDTH: usize = 20;
+
+        println!("{}", show.name);
+
+        for season in seasons {
+            let watched = season.watched * WIDTH / max(season.total, 1);
+            let aired = season.aired * WIDTH / max(season.total, 1);
+
+            println!(
+                "  Season {: >2} [{}{}{}] {: >3}%  {} watched / {} aired / {} total",
+                season.season,
+                "#".repeat(watched),
+                "-".repeat(aired - watched),
+                " ".repeat(WIDTH - aired),
+                season.watched * 100 / max(season.total, 1),
+                season.watched,
+                season.aired,
+                season.total
+            );
+        }
+
+        println!();
+    }
+
+    /// Formats episodes as table. If `local_files` is given, an additional column marks the
+    /// episodes that are available in the local media library.
+    pub fn format_episode_list_as_table<T: AsRef<Episode>>(
+        episodes: &[T],
+        separator: &HorizontalSeparator,
+        show_names: Option<&HashMap<usize, &str>>,
+        local_files: Option<&BTreeMap<usize, PathBuf>>,
+    ) -> String {
+        let mut table = String::new();
+
         // Calculate maximum length of episode name
         let max_ep_length = episodes
             .iter()
@@ -177,13 +280,17 @@ impl App {
         };
 
         if max_show_length > 0 {
-            print!("{: <width$} | ", "Show", width = max_show_length);
+            table.push_str(&format!("{: <width$} | ", "Show", width = max_show_length));
         }
-        println!(
+        table.push_str(&format!(
             "Season | Episode | {: <width$} | Air Date",
             "Name",
             width = max_ep_length
-        );
+        ));
+        if local_files.is_some() {
+            table.push_str("          | Local");
+        }
+        table.push('\n');
 
         let mut hline = if max_show_length > 0 {
             format!("{:-<width$}-|-", "-", width = max_show_length)
@@ -196,8 +303,11 @@ impl App {
             "-",
             width = max_ep_length
         ));
+        if local_files.is_some() {
+            hline.push_str("|------");
+        }
 
-        println!("{}", hline);
+        table.push_str(&format!("{}\n", hline));
 
         let mut current_season = 1;
         let mut current_week: u32 = 0;
@@ -218,7 +328,7 @@ impl App {
                 && ((separator == &HorizontalSeparator::Season && episode.season != current_season)
                     || (separator == &HorizontalSeparator::Week && this_week != current_week))
             {
-                println!("{}", hline);
+                table.push_str(&format!("{}\n", hline));
             }
             current_season = episode.season;
             current_week = this_week;
@@ -235,21 +345,37 @@ impl App {
                     "???"
                 };
 
-                print!("{: <width$} | ", name, width = max_show_length);
+                table.push_str(&format!("{: <width$} | ", name, width = max_show_length));
             };
 
-            println!(
-                "{: >6} | {: >7} | {: <width$} | {}",
+            table.push_str(&format!(
+                "{: >6} | {: >7} | {: <width$} | ",
                 episode.season,
                 episode.number,
                 episode.name,
-                air_date,
                 width = max_ep_length
-            );
+            ));
+            match local_files {
+                Some(local_files) => table.push_str(&format!(
+                    "{: <17} | {}\n",
+                    air_date,
+                    if local_files.contains_key(&episode.episode_id) {
+                        "yes"
+                    } else {
+                        ""
+                    }
+                )),
+                None => table.push_str(&format!("{}\n", air_date)),
+            }
         }
+
+        table
     }
 
-    fn print_show_list_as_table(shows: &[&Show], unwatched_episode_count: &HashMap<usize, usize>) {
+    fn print_show_list_as_table(
+        shows: &[&Show],
+        unwatched_episode_count: &HashMap<usize, (usize, usize)>,
+    ) {
         // Calculate maximum length of show and network name
         let (max_length_name, max_length_network) = shows
             .iter()
@@ -287,17 +413,27 @@ impl App {
             let status = &format!("{}", show.status);
 
             let unwatched = match unwatched_episode_count.get(&show.id) {
-                Some(count) => {
-                    if *count > 1 {
-                        format!("{} unwatched episodes", count)
+                Some(&(count, runtime)) => {
+                    if count > 1 {
+                        format!("{} unwatched episodes ({})", count, format_runtime(runtime))
                     } else {
-                        assert_eq!(1, *count);
-                        "1 unwatched episode".to_string()
+                        assert_eq!(1, count);
+                        format!("1 unwatched episode ({})", format_runtime(runtime))
                     }
                 }
                 None => "".to_string(),
             };
 
+            let unwatched = match show.viewing_state {
+                ViewingState::Dropped(Some(ref reason)) if unwatched.is_empty() => {
+                    format!("Dropped: {}", reason)
+                }
+                ViewingState::Dropped(Some(ref reason)) => {
+                    format!("{} (dropped: {})", unwatched, reason)
+                }
+                _ => unwatched,
+            };
+
             println!(
                 "{: <width_show_name$} | {: <width_network_name$} | {: <14} | {}",
                 show.name,
@@ -310,7 +446,7 @@ impl App {
         }
     }
 
-    fn get_episodes(&mut self, show: &Show) -> Result<(Vec<Episode>, (usize, usize))> {
+    fn get_episodes(&mut self, show: &mut Show) -> Result<(Vec<Episode>, (usize, usize))> {
         print!(
             "Have you already watched some episodes of {}? [y (yes); n (no)] ",
             show.name
@@ -322,6 +458,7 @@ impl App {
 
         let show_ids = [show.id];
         let mut episodes = self.api.get_episodes(&show_ids)?;
+        show.episode_counts = user_data::episode_counts(show.id, &episodes);
 
         // Remove episodes that haven't aired yet
         episodes.retain(|episode| match episode.airstamp {
@@ -335,7 +472,12 @@ impl App {
 
         let (season, number) = match answer.as_str().trim() {
             "y" | "yes" => {
-                App::print_episode_list_as_table(&episodes, &HorizontalSeparator::Season, None);
+                App::print_episode_list_as_table(
+                    &episodes,
+                    &HorizontalSeparator::Season,
+                    None,
+                    None,
+                );
                 println!();
                 println!("Specify the last episode you have watched:");
 
@@ -386,7 +528,7 @@ impl App {
         if let Some(mut show) = selected_show {
             println!("Added \"{}\".", show.name);
             println!();
-            let (episodes, last_watched) = self.get_episodes(&show)?;
+            let (episodes, last_watched) = self.get_episodes(&mut show)?;
 
             // Fill in information about last watched episode
             show.last_watched_episode = last_watched;
@@ -441,195 +583,1677 @@ impl App {
         Ok(())
     }
 
-    /// List all followed shows
-    pub fn list_shows(&self) -> Result<()> {
-        let subscribed_shows = self.user_data.subscribed_shows_by_most_recent();
+    /// List all followed shows (optionally only the ones with the given tag)
+    ///
+    /// If `progress` is set, prints the progress of each season below the table.
+    pub fn list_shows(&self, tag: Option<&str>, progress: bool, by_completion: bool) -> Result<()> {
+        let mut subscribed_shows: Vec<&Show> = self
+            .user_data
+            .subscribed_shows_by_most_recent()
+            .into_iter()
+            .filter(|show| show.matches_tag(tag))
+            .collect();
+
+        if by_completion {
+            // Most complete shows first
+            let completion = |show: &Show| {
+                let (watched, total) = self
+                    .user_data
+                    .season_progress(show)
+                    .iter()
+                    .fold((0, 0), |(watched, total), season| {
+                        (watched + season.watched, total + season.total)
+                    });
+                (watched * 1000).checked_div(total).unwrap_or(0)
+            };
+            subscribed_shows.sort_by_key(|show| Reverse(completion(show)));
+        }
 
         if subscribed_shows.is_empty() {
-            println!("You have not subscribed to any shows.");
+            match tag {
+                Some(tag) => println!("You have not subscribed to any shows tagged \"{}\".", tag),
+                None => println!("You have not subscribed to any shows."),
+            }
             return Ok(());
         }
 
-        let mut unwatched_episode_count: HashMap<usize, usize> = HashMap::new();
+        // Number of unwatched episodes and remaining watch time per show
+        let mut unwatched_episode_count: HashMap<usize, (usize, usize)> = HashMap::new();
 
         let unwatched_episodes = self.user_data.unwatched_episodes();
         for episode in unwatched_episodes.iter().filter(|episode| !episode.watched) {
-            *unwatched_episode_count.entry(episode.show_id).or_insert(0) += 1;
+            let entry = unwatched_episode_count
+                .entry(episode.show_id)
+                .or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += self.user_data.episode_runtime(episode).unwrap_or(0);
         }
 
-        println!("Subscribed shows:");
-        println!();
+        // Group shows by viewing state
+        let viewing_states = [
+            ViewingState::Watching,
+            ViewingState::PlanToWatch,
+            ViewingState::Paused,
+            ViewingState::Completed,
+            ViewingState::Dropped(None),
+        ];
+
+        for viewing_state in &viewing_states {
+            let shows: Vec<&Show> = subscribed_shows
+                .iter()
+                .filter(|show| {
+                    mem::discriminant(&show.viewing_state) == mem::discriminant(viewing_state)
+                })
+                .cloned()
+                .collect();
 
-        App::print_show_list_as_table(&subscribed_shows, &unwatched_episode_count);
-        println!();
+            if shows.is_empty() {
+                continue;
+            }
+
+            println!("{}:", viewing_state);
+            println!();
+
+            App::print_show_list_as_table(&shows, &unwatched_episode_count);
+            println!();
+
+            if progress {
+                for show in shows {
+                    App::print_season_progress(show, &self.user_data.season_progress(show));
+                }
+            }
+        }
 
         Ok(())
     }
 
-    /// List all unwatched episodes
-    pub fn list_episodes(&self) -> Result<()> {
-        let episodes = self.user_data.unwatched_episodes_oldest_first();
+    /// Add tags to (or remove tags from) a subscribed show
+    pub fn tag_show(&mut self, show: &str, tags: &[&str], remove: bool) -> Result<()> {
+        let show = match self.resolve_show(show)? {
+            Some(show) => show,
+            None => return Ok(()),
+        };
 
-        if episodes.is_empty() {
-            println!("You have no unwatched episodes!");
-            return Ok(());
+        let changed = if remove {
+            self.user_data.remove_tags(show.id, tags)
+        } else {
+            self.user_data.add_tags(show.id, tags)
+        };
+
+        if changed {
+            self.user_data.store()?;
+
+            let tags = self
+                .user_data
+                .subscribed_shows()
+                .iter()
+                .find(|subscribed_show| subscribed_show.id == show.id)
+                .map(|show| show.tags.join(", "))
+                .unwrap_or_default();
+
+            if tags.is_empty() {
+                println!("{} has no tags.", show.name);
+            } else {
+                println!("Tags of {}: {}", show.name, tags);
+            }
         }
 
-        let mut show_names: HashMap<usize, &str> = HashMap::new();
+        Ok(())
+    }
 
-        let shows = self.user_data.subscribed_shows();
-        for show in shows {
-            show_names.insert(show.id, &show.name);
+    /// List all tags together with the shows that have them
+    pub fn list_tags(&self) -> Result<()> {
+        let mut collections: Vec<(&str, Vec<&str>)> = Vec::new();
+
+        for show in self.user_data.subscribed_shows() {
+            for tag in &show.tags {
+                match collections.iter().position(|&(name, _)| name == tag) {
+                    Some(index) => collections[index].1.push(&show.name),
+                    None => collections.push((tag, vec![&show.name])),
+                }
+            }
         }
 
-        println!("Unwatched episodes:");
-        println!();
+        if collections.is_empty() {
+            println!("You have not tagged any shows.");
+            return Ok(());
+        }
 
-        App::print_episode_list_as_table(&episodes, &HorizontalSeparator::Week, Some(&show_names));
-        println!();
+        collections.sort();
+
+        for (tag, shows) in collections {
+            println!("{} ({}): {}", tag, shows.len(), shows.join(", "));
+        }
 
         Ok(())
     }
 
-    /// Mark episode(s) as watched
-    pub fn mark_as_watched(
+    /// Attach a note to a subscribed show, or delete a note (numbered as in `info`)
+    pub fn edit_notes(
         &mut self,
         show: &str,
-        season: Option<usize>,
-        episode: Option<usize>,
+        note: Option<&str>,
+        delete: Option<usize>,
     ) -> Result<()> {
-        let search_results = self
-            .api
-            .search_shows(show)
-            .chain_err(|| format!("Unable to search for show [\"{}\"]", show))?;
+        let show = match self.resolve_show(show)? {
+            Some(show) => show,
+            None => return Ok(()),
+        };
 
-        let matched_shows = self.match_with_subscribed_shows(&search_results);
+        if let Some(note) = note {
+            if self.user_data.add_note(show.id, note) {
+                println!("Added note to {}.", show.name);
+            }
+        }
 
-        if matched_shows.is_empty() {
-            println!("No matching show found.");
-            return Ok(());
+        if let Some(index) = delete {
+            if !self.user_data.delete_note(show.id, index) {
+                bail!("{} has no note number {}", show.name, index);
+            }
+            println!("Deleted note {} of {}.", index, show.name);
         }
 
-        let show_to_update = if matched_shows.len() > 1 {
-            match self.select_show(&matched_shows)? {
-                Some(show) => show,
-                None => {
-                    println!("No matching show found.");
-                    return Ok(());
-                }
+        self.user_data.store()
+    }
+
+    /// Set a custom field of a subscribed show, or remove it if no value is given
+    pub fn edit_field(&mut self, show: &str, key: &str, value: Option<&str>) -> Result<()> {
+        let show = match self.resolve_show(show)? {
+            Some(show) => show,
+            None => return Ok(()),
+        };
+
+        match (value, self.user_data.set_field(show.id, key, value)) {
+            (Some(value), true) => println!("Set {} of {} to \"{}\".", key, show.name, value),
+            (None, true) => println!("Removed {} of {}.", key, show.name),
+            (_, false) => {
+                println!("{} has no field {}.", show.name, key);
+                return Ok(());
             }
-        } else {
-            matched_shows[0]
+        }
+
+        self.user_data.store()
+    }
+
+    /// Print everything we know about a subscribed show: meta data from TVmaze, our progress
+    /// and a per-season breakdown, as well as tags, custom fields and notes
+    pub fn show_info(&mut self, show: &str) -> Result<()> {
+        let show = match self.resolve_show(show)? {
+            Some(show) => show,
+            None => return Ok(()),
+        };
+
+        // Get current meta data and all episodes
+        let details = self
+            .api
+            .get_shows(&[show.id])
+            .chain_err(|| format!("Unable to get details of [\"{}\"]", show.name))?
+            .pop()
+            .unwrap_or_else(|| show.clone());
+        let episodes = self
+            .api
+            .get_episodes(&[show.id])
+            .chain_err(|| format!("Unable to get episodes of [\"{}\"]", show.name))?;
+
+        let now = Utc::now();
+        let watched_after_gap: Vec<usize> = self
+            .user_data
+            .unwatched_episodes()
+            .iter()
+            .filter(|episode| episode.show_id == show.id && episode.watched)
+            .map(|episode| episode.episode_id)
+            .collect();
+        let is_watched = |episode: &Episode| {
+            (episode.season, episode.number) <= show.last_watched_episode
+                || watched_after_gap.contains(&episode.episode_id)
+        };
+        let is_aired = |episode: &Episode| match episode.airstamp {
+            Some(airstamp) => airstamp <= now,
+            None => false,
         };
+        let runtime = |episode: &Episode| episode.runtime.or(details.runtime).unwrap_or(0);
 
         if self.verbose {
             println!();
         }
 
-        let last_marked = self
-            .user_data
-            .mark_as_watched(show_to_update.id, season, episode);
-
-        if let Some(last_marked) = last_marked {
-            if let Some(season) = season {
-                if let Some(episode) = episode {
-                    println!(
-                        "Marked season {} episode {} of {} as watched.",
-                        season, episode, show_to_update.name
-                    );
-                } else {
-                    println!(
-                        "Marked season {} of {} as watched.",
-                        season, show_to_update.name
-                    );
-                }
+        println!("{}", details);
+        println!();
+        println!("Status:         {}", details.status);
+        if let Some(ref premiered) = details.premiered {
+            println!("Premiered:      {}", premiered);
+        }
+        if !details.genres.is_empty() {
+            println!("Genres:         {}", details.genres.join(", "));
+        }
+        if !details.schedule.days.is_empty() {
+            let days: Vec<String> = details
+                .schedule
+                .days
+                .iter()
+                .map(|day| format!("{}s", day))
+                .collect();
+            if details.schedule.time.is_empty() {
+                println!("Schedule:       {}", days.join(", "));
             } else {
                 println!(
-                    "Marked season {} episode {} of {} as watched.",
-                    last_marked.0, last_marked.1, show_to_update.name
+                    "Schedule:       {} at {}",
+                    days.join(", "),
+                    details.schedule.time
                 );
             }
-
-            self.user_data.store()?;
+        }
+        if let Some(runtime) = details.runtime {
+            println!("Runtime:        {} min", runtime);
+        }
+        if let Some(summary) = details.plain_summary() {
+            println!();
+            println!("{}", summary);
         }
 
-        Ok(())
-    }
+        // Our progress
+        let unwatched: Vec<&Episode> = episodes
+            .iter()
+            .filter(|episode| is_aired(episode) && !is_watched(episode))
+            .collect();
 
-    /// Update TV shows and episodes
-    pub fn update(&mut self, force: bool) -> Result<()> {
-        // Get TV show meta data
-        let mut show_ids = Vec::new();
-        for show in self.user_data.subscribed_shows() {
-            show_ids.push(show.id);
+        println!();
+        println!("Viewing state:  {}", show.viewing_state);
+        if show.priority != 0 {
+            println!("Priority:       {}", show.priority);
         }
-
-        if show_ids.is_empty() {
-            return Ok(());
+        if let ViewingState::Dropped(Some(ref reason)) = show.viewing_state {
+            println!("Dropped:        {}", reason);
+        }
+        if show.last_watched_episode != (0, 0) {
+            println!(
+                "Last watched:   Season {} episode {}",
+                show.last_watched_episode.0, show.last_watched_episode.1
+            );
+        }
+        println!(
+            "Unwatched:      {} episode(s), {}",
+            unwatched.len(),
+            format_runtime(unwatched.iter().map(|episode| runtime(episode)).sum())
+        );
+        if !show.tags.is_empty() {
+            println!("Tags:           {}", show.tags.join(", "));
         }
 
-        let shows = self.api.get_shows_sequential(&show_ids)?;
+        // Per-season breakdown
+        let mut seasons: Vec<usize> = episodes.iter().map(|episode| episode.season).collect();
+        seasons.sort();
+        seasons.dedup();
 
-        if self.verbose {
+        if !seasons.is_empty() {
             println!();
+            println!("Season | Episodes | Watched | Unwatched | Upcoming | Remaining");
+            println!("-------|----------|---------|-----------|----------|----------");
         }
 
-        // Update user data
-        show_ids.clear();
-        for show in shows {
-            let id = show.id;
-            if self.user_data.update_show(show) || force {
-                show_ids.push(id);
-            }
+        for season in seasons {
+            let season_episodes: Vec<&Episode> = episodes
+                .iter()
+                .filter(|episode| episode.season == season)
+                .collect();
+            let watched = season_episodes
+                .iter()
+                .filter(|episode| is_watched(episode))
+                .count();
+            let season_unwatched: Vec<&&Episode> = season_episodes
+                .iter()
+                .filter(|episode| is_aired(episode) && !is_watched(episode))
+                .collect();
+            let upcoming = season_episodes
+                .iter()
+                .filter(|episode| !is_aired(episode) && !is_watched(episode))
+                .count();
+            let remaining: usize = season_unwatched
+                .iter()
+                .map(|episode| runtime(episode))
+                .sum();
+
+            println!(
+                "{: >6} | {: >8} | {: >7} | {: >9} | {: >8} | {}",
+                season,
+                season_episodes.len(),
+                watched,
+                season_unwatched.len(),
+                upcoming,
+                if remaining > 0 {
+                    format_runtime(remaining)
+                } else {
+                    String::new()
+                }
+            );
         }
 
-        // Get episode data
-        let mut episodes = self.api.get_episodes_sequential(&show_ids)?;
+        if !show.fields.is_empty() {
+            println!();
+            for (key, value) in &show.fields {
+                println!("{}: {}", key, value);
+            }
+        }
 
-        if self.verbose {
+        if !show.notes.is_empty() {
             println!();
+            println!("Notes:");
+            for (i, note) in show.notes.iter().enumerate() {
+                println!("  {}. {}", i + 1, note);
+            }
         }
+        println!();
 
-        // Remove all episodes that haven't aired yet
-        episodes.retain(|episode| match episode.airstamp {
-            Some(airstamp) => Utc::now() >= airstamp,
-            None => false,
-        });
+        Ok(())
+    }
 
-        // Remove all episodes that have already been watched
-        {
-            let mut index = 0;
-            let mut current_show = 0;
-            let subscribed_shows = self.user_data.subscribed_shows();
-            episodes.retain(|episode| {
-                if episode.show_id != current_show {
-                    index = match subscribed_shows
-                        .iter()
-                        .position(|show| show.id == episode.show_id)
-                    {
-                        Some(index) => index,
-                        None => return false,
-                    };
-                    current_show = episode.show_id;
-                }
+    /// Search names, tags, custom fields and notes of subscribed shows
+    pub fn search(&self, query: &str) -> Result<()> {
+        let mut found = false;
 
-                let last_watched = subscribed_shows[index].last_watched_episode;
-                if episode.season == last_watched.0 {
-                    episode.number > last_watched.1
-                } else {
-                    episode.season > last_watched.0
-                }
-            });
+        for show in self.user_data.subscribed_shows() {
+            let matches = show.search_user_data(query);
+            if matches.is_empty() {
+                continue;
+            }
+
+            println!("{}:", show.name);
+            for text in matches {
+                println!("  {}", text);
+            }
+            found = true;
         }
 
-        // Update user data
-        // TODO: maybe store both id and (season, number) in last_watched_episode field?
+        if !found {
+            println!("No matching shows found.");
+        }
+
+        Ok(())
+    }
+
+    /// Set the priority of a subscribed show, which is used by `plan`
+    pub fn set_priority(&mut self, show: &str, priority: i32) -> Result<()> {
+        let show = match self.resolve_show(show)? {
+            Some(show) => show,
+            None => return Ok(()),
+        };
+
+        if self.user_data.set_priority(show.id, priority) {
+            println!("Set priority of {} to {}.", show.name, priority);
+        }
+
+        self.user_data.store()
+    }
+
+    /// Plan when to watch the backlog of unwatched episodes
+    ///
+    /// Episodes are planned in order of show priority and then in air order. If `per_day` (in
+    /// minutes) is given, the episodes are distributed accordingly and it is checked whether
+    /// the backlog can be finished by `until`. Otherwise, the minutes per day that are needed
+    /// to finish the backlog by `until` are calculated.
+    pub fn plan(
+        &self,
+        until: Option<NaiveDate>,
+        per_day: Option<usize>,
+        tag: Option<&str>,
+    ) -> Result<()> {
+        let shows = self.user_data.subscribed_shows();
+        let priority = |episode: &Episode| {
+            shows
+                .iter()
+                .find(|show| show.id == episode.show_id)
+                .map_or(0, |show| show.priority)
+        };
+
+        let mut episodes: Vec<&Episode> = self
+            .user_data
+            .unwatched_episodes_oldest_first()
+            .into_iter()
+            .filter(|episode| !episode.watched)
+            .filter(|episode| {
+                shows.iter().any(|show| {
+                    show.id == episode.show_id
+                        && show.matches_tag(tag)
+                        && !show.viewing_state.is_inactive()
+                })
+            })
+            .collect();
+
+        if episodes.is_empty() {
+            println!("You have no unwatched episodes!");
+            return Ok(());
+        }
+
+        // Stable sort, so that air order is kept for episodes of the same priority
+        episodes.sort_by_key(|episode| Reverse(priority(episode)));
+
+        let unknown_runtime = episodes
+            .iter()
+            .filter(|episode| self.user_data.episode_runtime(episode).is_none())
+            .count();
+        let backlog: Vec<(&Episode, usize)> = episodes
+            .into_iter()
+            .map(|episode| {
+                (
+                    episode,
+                    self.user_data.episode_runtime(episode).unwrap_or(0),
+                )
+            })
+            .collect();
+        let total: usize = backlog.iter().map(|&(_, runtime)| runtime).sum();
+
+        println!(
+            "Backlog: {} episode(s), {}",
+            backlog.len(),
+            format_runtime(total)
+        );
+        if unknown_runtime > 0 {
+            println!(
+                "({} episode(s) with unknown runtime are not accounted for)",
+                unknown_runtime
+            );
+        }
+        println!();
+
+        let today = Local::today().naive_local();
+        let minimum_per_day = match until {
+            Some(until) => match plan::minimum_per_day(&backlog, today, until) {
+                Some(minimum_per_day) => Some(minimum_per_day),
+                None => bail!("Deadline {} lies in the past", until),
+            },
+            None => None,
+        };
+
+        let per_day = match (per_day, minimum_per_day) {
+            (Some(per_day), Some(minimum_per_day)) => {
+                let days = plan::schedule(&backlog, today, per_day);
+                let finished = days.last().map_or(today, |day| day.date);
+                let until = until.unwrap();
+
+                if finished > until {
+                    println!(
+                        "Can't finish the backlog by {} with {} per day, it would take until {}.",
+                        until.format("%a, %b %d, %Y"),
+                        format_runtime(per_day),
+                        finished.format("%a, %b %d, %Y")
+                    );
+                    println!(
+                        "You would need to watch {} per day.",
+                        format_runtime(minimum_per_day)
+                    );
+                    return Ok(());
+                }
+
+                per_day
+            }
+            (Some(per_day), None) => per_day,
+            (None, Some(minimum_per_day)) => {
+                println!(
+                    "Watch {} per day to finish the backlog by {}.",
+                    format_runtime(minimum_per_day),
+                    until.unwrap().format("%a, %b %d, %Y")
+                );
+                println!();
+                minimum_per_day
+            }
+            (None, None) => bail!("Either a deadline or the time per day must be given"),
+        };
+
+        let show_names: HashMap<usize, &str> = shows
+            .iter()
+            .map(|show| (show.id, show.name.as_str()))
+            .collect();
+
+        for day in plan::schedule(&backlog, today, per_day) {
+            println!(
+                "{} ({})",
+                day.date.format("%a, %b %d, %Y"),
+                format_runtime(day.runtime)
+            );
+            for episode in day.episodes {
+                println!(
+                    "    {} S{:02}E{:02} {}",
+                    show_names.get(&episode.show_id).unwrap_or(&"???"),
+                    episode.season,
+                    episode.number,
+                    episode.name
+                );
+            }
+        }
+        println!();
+
+        Ok(())
+    }
+
+    /// Suggest unwatched episodes that fit into the given number of minutes
+    pub fn pick(&self, minutes: usize, tag: Option<&str>, random: bool) -> Result<()> {
+        let shows: Vec<&Show> = self
+            .user_data
+            .subscribed_shows()
+            .iter()
+            .filter(|show| show.matches_tag(tag) && !show.viewing_state.is_inactive())
+            .collect();
+
+        let episodes: Vec<&Episode> = self
+            .user_data
+            .unwatched_episodes_oldest_first()
+            .into_iter()
+            .filter(|episode| !episode.watched)
+            .filter(|episode| shows.iter().any(|show| show.id == episode.show_id))
+            .collect();
+
+        // Episodes with unknown runtime never fit, which also holds back later episodes of the show
+        let backlog: Vec<(&Episode, usize)> = episodes
+            .into_iter()
+            .map(|episode| {
+                (
+                    episode,
+                    self.user_data
+                        .episode_runtime(episode)
+                        .unwrap_or(usize::MAX),
+                )
+            })
+            .collect();
+
+        let mut rng = rand::thread_rng();
+        let picked = plan::pick(&backlog, minutes, |candidates| {
+            if random {
+                rng.gen_range(0, candidates)
+            } else {
+                0
+            }
+        });
+
+        if picked.is_empty() {
+            println!(
+                "No unwatched episode fits into {}.",
+                format_runtime(minutes)
+            );
+            return Ok(());
+        }
+
+        let mut total = 0;
+        println!("In {} you can watch:", format_runtime(minutes));
+        println!();
+        for episode in picked {
+            let runtime = self.user_data.episode_runtime(episode).unwrap_or(0);
+            total += runtime;
+            println!(
+                "    {} S{:02}E{:02} {} ({})",
+                shows
+                    .iter()
+                    .find(|show| show.id == episode.show_id)
+                    .map_or("???", |show| show.name.as_str()),
+                episode.season,
+                episode.number,
+                episode.name,
+                format_runtime(runtime)
+            );
+        }
+        println!();
+        println!("Total: {}", format_runtime(total));
+
+        Ok(())
+    }
+
+    /// Print viewing statistics of the given year (or of all time), as table or as JSON
+    pub fn stats(&self, year: Option<i32>, json: bool) -> Result<()> {
+        let stats = Stats::new(
+            self.user_data.history(),
+            self.user_data.subscribed_shows(),
+            self.user_data.unwatched_episodes(),
+            year,
+            &Local,
+        );
+
+        if json {
+            println!("{}", ::serde_json::to_string_pretty(&stats)?);
+        } else {
+            print!("{}", stats.to_text());
+            println!();
+        }
+
+        Ok(())
+    }
+
+    /// Look up the TvMaze.com id of an imported show, by TheTVDB id first, then by IMDb id
+    ///
+    /// Shows without any id are searched by name and only accepted if the name matches exactly.
+    fn lookup_imported_show(&mut self, show: &ImportedShow) -> Result<Option<usize>> {
+        let mut ids = Vec::new();
+        if let Some(thetvdb) = show.thetvdb {
+            ids.push(("thetvdb", thetvdb.to_string()));
+        }
+        if let Some(ref imdb) = show.imdb {
+            ids.push(("imdb", imdb.clone()));
+        }
+
+        for (service, id) in ids {
+            if let Some(show_id) = self
+                .api
+                .lookup_show(service, &id)
+                .chain_err(|| format!("Unable to look up show [\"{}\"]", show.title))?
+            {
+                return Ok(Some(show_id));
+            }
+        }
+
+        if show.thetvdb.is_none() && show.imdb.is_none() {
+            let search_results = self
+                .api
+                .search_shows(&show.title)
+                .chain_err(|| format!("Unable to search for show [\"{}\"]", show.title))?;
+
+            return Ok(search_results
+                .into_iter()
+                .find(|result| result.show.name.to_lowercase() == show.title.to_lowercase())
+                .map(|result| result.show.id));
+        }
+
+        Ok(None)
+    }
+
+    /// Subscribe to imported shows and mark their watched episodes as watched
+    ///
+    /// Prints a report of shows and episodes that couldn't be mapped to TvMaze.com. With
+    /// `dry_run`, nothing is stored.
+    pub fn import(&mut self, imported_shows: Vec<ImportedShow>, dry_run: bool) -> Result<()> {
+        let mut unmapped = Vec::new();
+
+        for imported_show in imported_shows {
+            let show_id = match self.lookup_imported_show(&imported_show)? {
+                Some(show_id) => show_id,
+                None => {
+                    unmapped.push(format!("{}: Show not found", imported_show.title));
+                    continue;
+                }
+            };
+
+            let mut show = match self
+                .user_data
+                .subscribed_shows()
+                .iter()
+                .find(|show| show.id == show_id)
+            {
+                Some(show) => show.clone(),
+                None => self.api.get_shows_sequential(&[show_id])?.remove(0),
+            };
+
+            let mut episodes = self.api.get_episodes(&[show_id])?;
+            show.episode_counts = user_data::episode_counts(show_id, &episodes);
+
+            // Remove episodes that haven't aired yet
+            episodes.retain(|episode| match episode.airstamp {
+                Some(airstamp) => Utc::now() >= airstamp,
+                None => false,
+            });
+
+            let (watched, missing): (Vec<&ImportedEpisode>, Vec<&ImportedEpisode>) =
+                imported_show.episodes.iter().partition(|imported| {
+                    episodes.iter().any(|episode| {
+                        (episode.season, episode.number) == (imported.season, imported.number)
+                    })
+                });
+
+            if !missing.is_empty() {
+                let missing: Vec<String> = missing
+                    .iter()
+                    .map(|episode| format!("S{:02}E{:02}", episode.season, episode.number))
+                    .collect();
+                unmapped.push(format!(
+                    "{}: Episode(s) not found: {}",
+                    imported_show.title,
+                    missing.join(", ")
+                ));
+            }
+
+            if dry_run {
+                println!(
+                    "{}: {} watched episode(s) would be imported.",
+                    show.name,
+                    watched.len()
+                );
+            } else {
+                let watched: Vec<_> = watched
+                    .iter()
+                    .map(|episode| ((episode.season, episode.number), episode.watched))
+                    .collect();
+                let name = show.name.clone();
+                let newly_watched = self.user_data.import_watched(show, episodes, &watched);
+                println!("{}: {} episode(s) marked as watched.", name, newly_watched);
+            }
+        }
+
+        if !unmapped.is_empty() {
+            println!();
+            println!("Not imported:");
+            for entry in unmapped {
+                println!("    {}", entry);
+            }
+        }
+        println!();
+
+        if !dry_run {
+            self.user_data.store()?;
+        }
+
+        Ok(())
+    }
+
+    /// Mark episodes that have been played in Kodi as watched
+    ///
+    /// Kodi's TV shows are mapped to subscribed shows by their TheTVDB or IMDb id. Prints
+    /// which episodes have been marked per show, which are recorded in the watch history as
+    /// last played in Kodi. With `dry_run`, nothing is changed.
+    pub fn import_kodi(&mut self, kodi_shows: Vec<ImportedShow>, dry_run: bool) -> Result<()> {
+        let mut not_subscribed = Vec::new();
+        let mut changed = false;
+
+        // Mark on a copy, so that a dry run leaves the user data as is (the shell reuses it)
+        let mut user_data = self.user_data.clone();
+
+        for kodi_show in kodi_shows {
+            let (show_id, name) = match self.find_subscribed_show(&kodi_show) {
+                Some(show) => (show.id, show.name.clone()),
+                None => {
+                    not_subscribed.push(kodi_show.title);
+                    continue;
+                }
+            };
+
+            let marked: Vec<String> =
+                App::mark_played(&mut user_data, show_id, &kodi_show.episodes)
+                    .into_iter()
+                    .map(|(season, number)| format!("S{:02}E{:02}", season, number))
+                    .collect();
+
+            if marked.is_empty() {
+                println!("{}: No new episodes played.", name);
+            } else {
+                println!(
+                    "{}: {} {} as watched: {}",
+                    name,
+                    marked.len(),
+                    if dry_run {
+                        "episode(s) would be marked"
+                    } else {
+                        "episode(s) marked"
+                    },
+                    marked.join(", ")
+                );
+                changed = true;
+            }
+        }
+
+        if !not_subscribed.is_empty() {
+            println!();
+            println!("Not subscribed (or without TheTVDB or IMDb id):");
+            for title in not_subscribed {
+                println!("    {}", title);
+            }
+        }
+
+        if changed && !dry_run {
+            self.user_data = user_data;
+            self.user_data.store()?;
+        }
+
+        Ok(())
+    }
+
+    /// Print all user data in the portable export format
+    pub fn export(&self, format: &ExportFormat) -> Result<()> {
+        match *format {
+            ExportFormat::Bingers => {
+                let export = Export::new(&self.user_data);
+                println!("{}", ::serde_json::to_string_pretty(&export)?);
+            }
+            ExportFormat::Trakt => {
+                let (history, left_out) = TraktHistory::new(&self.user_data);
+                println!("{}", ::serde_json::to_string_pretty(&history)?);
+
+                if left_out > 0 {
+                    eprintln!(
+                        "Left out {} history entries of shows that are no longer subscribed \
+                         or have neither a TheTVDB nor an IMDb id.",
+                        left_out
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Import user data from the portable export format
+    ///
+    /// Either merges the export into the user data or replaces the user data. With `dry_run`,
+    /// nothing is changed.
+    pub fn import_export(&mut self, path: &Path, replace: bool, dry_run: bool) -> Result<()> {
+        let mut json = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut json))
+            .chain_err(|| format!("Unable to read [{}]", path.display()))?;
+
+        let (shows, history) = Export::from_json(&json)?.into_user_data()?;
+        let episode_count: usize = shows.iter().map(|(_, episodes)| episodes.len()).sum();
+
+        println!(
+            "{} {} show(s), {} unwatched episode(s) and {} history entries.",
+            match (dry_run, replace) {
+                (true, true) => "Would replace the user data with",
+                (true, false) => "Would merge",
+                (false, true) => "Replacing the user data with",
+                (false, false) => "Merging",
+            },
+            shows.len(),
+            episode_count,
+            history.len()
+        );
+
+        if dry_run {
+            return Ok(());
+        }
+
+        if replace {
+            let (shows, episodes): (Vec<Show>, Vec<Vec<Episode>>) = shows.into_iter().unzip();
+            self.user_data
+                .replace(shows, episodes.into_iter().flatten().collect(), history);
+        } else {
+            for (show, episodes) in shows {
+                self.user_data.merge_show(show, episodes);
+            }
+            self.user_data.merge_history(history);
+        }
+        self.user_data.store()?;
+
+        println!("Run \"bingers update\" to refresh show information from TVmaze.");
+
+        Ok(())
+    }
+
+    /// Ask a yes/no question on the terminal
+    fn confirm(question: &str) -> Result<bool> {
+        print!("{} [y (yes); n (no)] ", question);
+        let _ = io::stdout().flush();
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+
+        Ok(matches!(answer.trim(), "y" | "yes"))
+    }
+
+    /// Merge user data from another machine into the user data
+    pub fn merge(&mut self, path: &Path) -> Result<()> {
+        let other = UserData::load_file(path)
+            .chain_err(|| format!("Unable to load user data from {:?}", path))?;
+        self.merge_user_data(other, &path.display().to_string(), None)?;
+
+        println!("Merged {}.", path.display());
+
+        Ok(())
+    }
+
+    /// Merge other user data (from the given source) into the user data and store it
+    ///
+    /// Conflicts (e.g. a show that has been removed on one side) are resolved against the
+    /// common base, if there is one, and interactively otherwise.
+    pub fn merge_user_data(
+        &mut self,
+        other: UserData,
+        source: &str,
+        base: Option<&UserData>,
+    ) -> Result<()> {
+        self.user_data.merge(other, |conflict| {
+            if let Some(resolution) = base.and_then(|base| base.resolve_conflict(&conflict)) {
+                return Ok(resolution);
+            }
+
+            match conflict {
+                MergeConflict::OnlyInOther(show) => App::confirm(&format!(
+                    "{} is only subscribed in {}. Add it?",
+                    show.name, source
+                )),
+                MergeConflict::OnlyHere(show) => App::confirm(&format!(
+                    "{} isn't subscribed in {}. Remove it?",
+                    show.name, source
+                )),
+                MergeConflict::ViewingState(show, viewing_state) => App::confirm(&format!(
+                    "{} is {} here, but {} in {}. Mark it as {}?",
+                    show.name,
+                    show.viewing_state.to_string().to_lowercase(),
+                    viewing_state.to_string().to_lowercase(),
+                    source,
+                    viewing_state.to_string().to_lowercase()
+                )),
+            }
+        })?;
+
+        self.user_data.store()
+    }
+
+    /// Assign a viewing state to a subscribed show
+    pub fn set_viewing_state(&mut self, show: &str, viewing_state: ViewingState) -> Result<()> {
+        let show = match self.resolve_show(show)? {
+            Some(show) => show,
+            None => return Ok(()),
+        };
+
+        if self
+            .user_data
+            .set_viewing_state(show.id, viewing_state.clone())
+        {
+            self.user_data.store()?;
+            println!(
+                "Marked {} as {}.",
+                show.name,
+                viewing_state.to_string().to_lowercase()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Print per-season average ratings as well as the best and worst episodes of a show
+    pub fn ratings(&mut self, show: &str) -> Result<()> {
+        let show = match self.resolve_show(show)? {
+            Some(show) => show,
+            None => return Ok(()),
+        };
+
+        let ratings = self.user_data.ratings(show.id);
+        if ratings.is_empty() {
+            println!("You have not rated any episodes of {}.", show.name);
+            return Ok(());
+        }
+
+        println!("Ratings of {}:", show.name);
+        println!();
+
+        let mut seasons: Vec<usize> = ratings.iter().map(|entry| entry.season).collect();
+        seasons.dedup();
+
+        for season in seasons {
+            let season_ratings: Vec<u8> = ratings
+                .iter()
+                .filter(|entry| entry.season == season)
+                .filter_map(|entry| entry.rating)
+                .collect();
+            let average = season_ratings
+                .iter()
+                .map(|&rating| f64::from(rating))
+                .sum::<f64>()
+                / season_ratings.len() as f64;
+
+            println!(
+                "Season {:>2}: {:.1} ({} rated episode{})",
+                season,
+                average,
+                season_ratings.len(),
+                if season_ratings.len() == 1 { "" } else { "s" }
+            );
+        }
+
+        // Best episodes first, earlier episodes first among equally rated ones
+        let mut ranked = ratings.clone();
+        ranked.sort_by_key(|entry| Reverse(entry.rating));
+
+        let best_count = min(3, ranked.len());
+        let worst_count = min(3, ranked.len() - best_count);
+
+        let print_entries = |entries: &[&WatchedEpisode]| {
+            for entry in entries {
+                println!(
+                    "  S{:02}E{:02}  {:>2}/10  {}{}",
+                    entry.season,
+                    entry.number,
+                    entry.rating.unwrap_or(0),
+                    entry.name,
+                    match entry.note {
+                        Some(ref note) => format!(" - \"{}\"", note),
+                        None => String::new(),
+                    }
+                );
+            }
+        };
+
+        println!();
+        println!("Best episodes:");
+        print_entries(&ranked[..best_count]);
+
+        if worst_count > 0 {
+            let mut worst = ranked[ranked.len() - worst_count..].to_vec();
+            worst.reverse();
+
+            println!();
+            println!("Worst episodes:");
+            print_entries(&worst);
+        }
+        println!();
+
+        Ok(())
+    }
+
+    /// List all unwatched episodes (except the ones of paused or dropped shows)
+    ///
+    /// If a tag is given, only episodes of shows with that tag are listed.
+    pub fn list_episodes(&self, tag: Option<&str>) -> Result<()> {
+        let shows = self.user_data.subscribed_shows();
+
+        let inactive_shows: Vec<usize> = shows
+            .iter()
+            .filter(|show| show.viewing_state.is_inactive())
+            .map(|show| show.id)
+            .collect();
+
+        let tagged_shows: Vec<usize> = shows
+            .iter()
+            .filter(|show| show.matches_tag(tag))
+            .map(|show| show.id)
+            .collect();
+
+        let (hidden_episodes, episodes): (Vec<&Episode>, Vec<&Episode>) = self
+            .user_data
+            .unwatched_episodes_oldest_first()
+            .into_iter()
+            .filter(|episode| tagged_shows.contains(&episode.show_id))
+            .partition(|episode| inactive_shows.contains(&episode.show_id));
+
+        if episodes.is_empty() {
+            println!("You have no unwatched episodes!");
+            if !hidden_episodes.is_empty() {
+                println!(
+                    "{} episode(s) of paused or dropped shows not shown.",
+                    hidden_episodes.len()
+                );
+            }
+            return Ok(());
+        }
+
+        let mut show_names: HashMap<usize, &str> = HashMap::new();
+
+        for show in shows {
+            show_names.insert(show.id, &show.name);
+        }
+
+        println!("Unwatched episodes:");
+        println!();
+
+        // Only mark episodes available locally, if the media library has been scanned
+        let library = library::Library::load(
+            self.user_data.directory(),
+            self.user_data.unwatched_episodes(),
+        )?;
+        let local_files = Some(library.files()).filter(|files| !files.is_empty());
+        App::print_episode_list_as_table(
+            &episodes,
+            &HorizontalSeparator::Week,
+            Some(&show_names),
+            local_files,
+        );
+        println!();
+
+        if !hidden_episodes.is_empty() {
+            println!(
+                "{} episode(s) of paused or dropped shows not shown.",
+                hidden_episodes.len()
+            );
+            println!();
+        }
+
+        Ok(())
+    }
+
+    /// Scan a media library for video files of unwatched episodes
+    pub fn library_scan(&self, directory: &Path) -> Result<()> {
+        let paths = library::scan(directory)?;
+        let files: Vec<library::MediaFile> = paths
+            .iter()
+            .filter_map(|path| library::parse_path(path))
+            .collect();
+
+        let (local_files, unmatched) = library::match_files(
+            &files,
+            self.user_data.subscribed_shows(),
+            self.user_data.unwatched_episodes(),
+        );
+
+        println!(
+            "Found {} video file(s), {} of them unwatched episodes.",
+            paths.len(),
+            local_files.len()
+        );
+        if !unmatched.is_empty() {
+            println!();
+            println!("Not matched to an unwatched episode:");
+            for file in unmatched {
+                println!("    {}", file.path.display());
+            }
+        }
+
+        let episodes = self.user_data.unwatched_episodes();
+        let mut library = library::Library::load(self.user_data.directory(), episodes)?;
+        library.set_files(local_files);
+        library.store(episodes)
+    }
+
+    /// List aired, unwatched episodes that aren't available in the media library
+    pub fn library_missing(&self, tag: Option<&str>) -> Result<()> {
+        let shows = self.user_data.subscribed_shows();
+        let library = library::Library::load(
+            self.user_data.directory(),
+            self.user_data.unwatched_episodes(),
+        )?;
+        let local_files = library.files();
+        let now = Utc::now();
+
+        let active_shows: Vec<usize> = shows
+            .iter()
+            .filter(|show| show.matches_tag(tag) && !show.viewing_state.is_inactive())
+            .map(|show| show.id)
+            .collect();
+
+        let episodes: Vec<&Episode> = self
+            .user_data
+            .unwatched_episodes_oldest_first()
+            .into_iter()
+            .filter(|episode| {
+                active_shows.contains(&episode.show_id)
+                    && !episode.watched
+                    && !local_files.contains_key(&episode.episode_id)
+                    && match episode.airstamp {
+                        Some(airstamp) => airstamp <= now,
+                        None => false,
+                    }
+            })
+            .collect();
+
+        if episodes.is_empty() {
+            println!("All aired episodes are available locally.");
+            return Ok(());
+        }
+
+        let show_names: HashMap<usize, &str> = shows
+            .iter()
+            .map(|show| (show.id, show.name.as_str()))
+            .collect();
+
+        println!("Aired episodes missing from the media library:");
+        println!();
+
+        App::print_episode_list_as_table(
+            &episodes,
+            &HorizontalSeparator::Week,
+            Some(&show_names),
+            None,
+        );
+        println!();
+
+        Ok(())
+    }
+
+    /// List the next unwatched episode of each show
+    pub fn next_up(&self, tag: Option<&str>, order: &NextUpOrder) -> Result<()> {
+        let shows: HashMap<usize, &Show> = self
+            .user_data
+            .subscribed_shows()
+            .iter()
+            .filter(|show| show.matches_tag(tag) && !show.viewing_state.is_inactive())
+            .map(|show| (show.id, show))
+            .collect();
+
+        let mut next_episodes: Vec<(&Show, &Episode, usize)> = self
+            .user_data
+            .next_episodes()
+            .into_iter()
+            .filter_map(|(episode, remaining)| {
+                shows
+                    .get(&episode.show_id)
+                    .map(|&show| (show, episode, remaining))
+            })
+            .collect();
+
+        if next_episodes.is_empty() {
+            println!("You have no unwatched episodes!");
+            return Ok(());
+        }
+
+        // Stable sorts, so that air order is kept for shows that compare equal
+        next_episodes.sort_by_key(|&(_, episode, _)| episode.airstamp);
+        match *order {
+            NextUpOrder::LastActivity => next_episodes
+                .sort_by_key(|&(show, _, _)| Reverse(self.user_data.last_activity(show.id))),
+            NextUpOrder::AirDate => {}
+            NextUpOrder::Priority => {
+                next_episodes.sort_by_key(|&(show, _, _)| Reverse(show.priority))
+            }
+        }
+
+        let max_length_name = next_episodes
+            .iter()
+            .map(|&(show, _, _)| show.name.len())
+            .max()
+            .unwrap_or(0);
+
+        println!("Next up:");
+        println!();
+        for (show, episode, remaining) in next_episodes {
+            let air_date = match episode.airstamp {
+                Some(airstamp) => format!("{}", airstamp.format("%b %d, %Y")),
+                None => "TBD".to_string(),
+            };
+
+            println!(
+                "{: <width$} | S{:02}E{:02} | {: <12} | {} | {} unwatched",
+                show.name,
+                episode.season,
+                episode.number,
+                air_date,
+                episode.name,
+                remaining,
+                width = max_length_name,
+            );
+        }
+        println!();
+
+        Ok(())
+    }
+
+    /// Mark episode(s) as watched
+    ///
+    /// A rating (1 - 10) and a note can be attached to a single episode.
+    pub fn mark_as_watched(
+        &mut self,
+        show: &str,
+        season: Option<usize>,
+        episode: Option<usize>,
+        rating: Option<u8>,
+        note: Option<String>,
+    ) -> Result<()> {
+        if let Some(rating) = rating {
+            if !(1..=10).contains(&rating) {
+                bail!("Rating must be between 1 and 10 [{}]", rating);
+            }
+        }
+
+        if (rating.is_some() || note.is_some()) && season.is_some() && episode.is_none() {
+            bail!("Ratings and notes can only be attached to a single episode");
+        }
+
+        let show_to_update = match self.resolve_show(show)? {
+            Some(show) => show,
+            None => return Ok(()),
+        };
+
+        let last_marked = self.mark_as_watched_by_id(show_to_update.id, season, episode)?;
+
+        if let Some(last_marked) = last_marked {
+            if let Some(season) = season {
+                if let Some(episode) = episode {
+                    println!(
+                        "Marked season {} episode {} of {} as watched.",
+                        season, episode, show_to_update.name
+                    );
+                } else {
+                    println!(
+                        "Marked season {} of {} as watched.",
+                        season, show_to_update.name
+                    );
+                }
+            } else {
+                println!(
+                    "Marked season {} episode {} of {} as watched.",
+                    last_marked.0, last_marked.1, show_to_update.name
+                );
+            }
+        }
+
+        if rating.is_some() || note.is_some() {
+            // An episode that has been watched already is rated in its history entry
+            let (season, number) = match (last_marked, season, episode) {
+                (Some(last_marked), _, _) => last_marked,
+                (None, Some(season), Some(episode)) => (season, episode),
+                _ => bail!("Episode not found or already watched, rating not saved"),
+            };
+
+            if !self
+                .user_data
+                .rate_episode(show_to_update.id, season, number, rating, note)
+            {
+                bail!("Episode not found in watch history, rating not saved");
+            }
+            if last_marked.is_none() {
+                println!(
+                    "Rated season {} episode {} of {}.",
+                    season, number, show_to_update.name
+                );
+            }
+            self.user_data.store()?;
+        }
+
+        Ok(())
+    }
+
+    /// Mark episode(s) of a subscribed show as watched and store user data
+    ///
+    /// Returns episode number of last episode that was marked as watched.
+    pub fn mark_as_watched_by_id(
+        &mut self,
+        show_id: usize,
+        season: Option<usize>,
+        episode: Option<usize>,
+    ) -> Result<Option<(usize, usize)>> {
+        let last_marked = self.user_data.mark_as_watched(show_id, season, episode);
+
+        if last_marked.is_some() {
+            self.user_data.store()?;
+        }
+
+        Ok(last_marked)
+    }
+
+    /// Mark episode(s) as unwatched
+    pub fn mark_as_unwatched(
+        &mut self,
+        show: &str,
+        season: Option<usize>,
+        episode: Option<usize>,
+    ) -> Result<()> {
+        let show_to_update = match self.resolve_show(show)? {
+            Some(show) => show,
+            None => return Ok(()),
+        };
+
+        let first_marked = self.mark_as_unwatched_by_id(show_to_update.id, season, episode)?;
+
+        match first_marked {
+            Some(first_marked) => {
+                if season.is_some() && episode.is_none() {
+                    println!(
+                        "Marked season {} of {} as unwatched.",
+                        first_marked.0, show_to_update.name
+                    );
+                } else {
+                    println!(
+                        "Marked season {} episode {} of {} as unwatched.",
+                        first_marked.0, first_marked.1, show_to_update.name
+                    );
+                }
+            }
+            None => println!("No watched episode found."),
+        }
+
+        Ok(())
+    }
+
+    /// Mark episode(s) of a subscribed show as unwatched and store user data
+    ///
+    /// Calls web API to restore episodes that have already been removed from the list of
+    /// unwatched episodes.
+    ///
+    /// Returns episode number of first episode that was marked as unwatched.
+    pub fn mark_as_unwatched_by_id(
+        &mut self,
+        show_id: usize,
+        season: Option<usize>,
+        episode: Option<usize>,
+    ) -> Result<Option<(usize, usize)>> {
+        let mut episodes = self.api.get_episodes(&[show_id])?;
+
+        if self.verbose {
+            println!();
+        }
+
+        // Remove episodes that haven't aired yet
+        episodes.retain(|episode| match episode.airstamp {
+            Some(airstamp) => Utc::now() >= airstamp,
+            None => false,
+        });
+
+        let first_marked = self
+            .user_data
+            .mark_as_unwatched(show_id, season, episode, episodes);
+
+        if first_marked.is_some() {
+            self.user_data.store()?;
+        }
+
+        Ok(first_marked)
+    }
+
+    pub fn user_data(&self) -> &UserData {
+        &self.user_data
+    }
+
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+        self.api.set_verbose(verbose);
+    }
+
+    /// Reload user data from disk
+    ///
+    /// Long-running commands reload before changing the user data, so that changes stored
+    /// by other invocations of bingers in the meantime aren't overwritten.
+    pub fn reload(&mut self) -> Result<()> {
+        self.user_data = UserData::load()?;
+
+        Ok(())
+    }
+
+    /// Update TV shows and episodes (optionally only of shows with the given tag), then sync
+    /// played episodes with Jellyfin (if configured)
+    ///
+    /// Returns the number of new episodes.
+    pub fn update(&mut self, force: bool, tag: Option<&str>) -> Result<usize> {
+        let shows = self.subscribed_shows_last_updated(tag);
+
+        let new_episodes = if shows.is_empty() {
+            0
+        } else {
+            let (shows, episodes) = App::fetch_updates(&mut self.api, &shows, force, self.verbose)?;
+            self.apply_updates(shows, episodes)?.0
+        };
+
+        // The updates are stored already, so failing to sync with Jellyfin is only a warning
+        let synced = Config::load().and_then(|config| match config.jellyfin {
+            Some(jellyfin) => self.sync_jellyfin(&jellyfin, tag),
+            None => Ok(()),
+        });
+        if let Err(e) = synced {
+            eprintln!(
+                "Warning: Unable to sync played episodes with Jellyfin: {}",
+                e
+            );
+        }
+
+        Ok(new_episodes)
+    }
+
+    /// Subscribed show with the TheTVDB or IMDb id of the imported show
+    fn find_subscribed_show(&self, imported_show: &ImportedShow) -> Option<&Show> {
+        self.user_data.subscribed_shows().iter().find(|show| {
+            (show.externals.thetvdb.is_some() && show.externals.thetvdb == imported_show.thetvdb)
+                || (show.externals.imdb.is_some() && show.externals.imdb == imported_show.imdb)
+        })
+    }
+
+    /// Marks episodes of a subscribed show that have been played on a media server as watched,
+    /// in episode order. The watch history records when they have been played, if known.
+    ///
+    /// Returns the newly watched episodes.
+    fn mark_played(
+        user_data: &mut UserData,
+        show_id: usize,
+        played: &[ImportedEpisode],
+    ) -> Vec<(usize, usize)> {
+        let mut played: Vec<&ImportedEpisode> = played.iter().collect();
+        played.sort_by_key(|episode| (episode.season, episode.number));
+
+        let mut marked = Vec::new();
+        for episode in played {
+            if user_data
+                .mark_as_watched(show_id, Some(episode.season), Some(episode.number))
+                .is_none()
+            {
+                continue;
+            }
+
+            if let Some(watched) = episode.watched {
+                user_data.set_watched_time(show_id, episode.season, episode.number, watched);
+            }
+            marked.push((episode.season, episode.number));
+        }
+
+        marked
+    }
+
+    /// Mark episodes that have been played on a Jellyfin server as watched
+    ///
+    /// Only shows that are subscribed already are synchronized, they are mapped by their
+    /// TheTVDB or IMDb id (use `import jellyfin` to subscribe to other shows). Optionally only
+    /// shows with the given tag are synchronized.
+    pub fn sync_jellyfin(&mut self, config: &JellyfinConfig, tag: Option<&str>) -> Result<()> {
+        let mut changed = false;
+
+        for imported_show in Jellyfin::new(config)?.played_shows()? {
+            let (show_id, name) = match self.find_subscribed_show(&imported_show) {
+                Some(show) if show.matches_tag(tag) => (show.id, show.name.clone()),
+                _ => continue,
+            };
+
+            let newly_watched =
+                App::mark_played(&mut self.user_data, show_id, &imported_show.episodes).len();
+            if newly_watched > 0 {
+                println!(
+                    "{}: {} episode(s) played on Jellyfin marked as watched.",
+                    name, newly_watched
+                );
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.user_data.store()?;
+        }
+
+        Ok(())
+    }
+
+    /// Ids of subscribed shows together with the time of their last update.
+    pub fn subscribed_shows_last_updated(&self, tag: Option<&str>) -> Vec<(usize, u64)> {
+        self.user_data
+            .subscribed_shows()
+            .iter()
+            .filter(|show| show.matches_tag(tag))
+            // Paused and dropped shows catch up once they are resumed, because their
+            // last_updated field remains unchanged in the meantime
+            .filter(|show| !show.viewing_state.is_inactive())
+            .map(|show| (show.id, show.last_updated))
+            .collect()
+    }
+
+    /// Fetches meta data of the given shows and episodes of all shows that have changed
+    /// since their last update (or of all shows, if `force` is set).
+    ///
+    /// Doesn't touch the user data, so that it can be called on a separate thread (with a
+    /// separate instance of the web API).
+    pub fn fetch_updates(
+        api: &mut TvMazeApi,
+        shows: &[(usize, u64)],
+        force: bool,
+        verbose: bool,
+    ) -> Result<(Vec<Show>, Vec<Episode>)> {
+        // Get TV show meta data
+        let show_ids: Vec<usize> = shows.iter().map(|&(id, _)| id).collect();
+        let updated_shows = api.get_shows_sequential(&show_ids)?;
+
+        if verbose {
+            println!();
+        }
+
+        // Get episode data of changed shows
+        let show_ids: Vec<usize> = updated_shows
+            .iter()
+            .filter(|show| {
+                force
+                    || shows.iter().any(|&(id, last_updated)| {
+                        id == show.id && last_updated != show.last_updated
+                    })
+            })
+            .map(|show| show.id)
+            .collect();
+        let episodes = api.get_episodes_sequential(&show_ids)?;
+
+        if verbose {
+            println!();
+        }
+
+        Ok((updated_shows, episodes))
+    }
+
+    /// Applies shows and episodes returned by `fetch_updates()` to the user data.
+    ///
+    /// Returns the number of new episodes and descriptions of changed shows and episodes
+    /// (which are printed as well, unless the app is quiet).
+    pub fn apply_updates(
+        &mut self,
+        shows: Vec<Show>,
+        mut episodes: Vec<Episode>,
+    ) -> Result<(usize, Vec<String>)> {
+        // Update user data
+        let mut changes = Vec::new();
+        for show in shows {
+            self.user_data.update_show(show, &mut changes);
+        }
+        self.user_data.set_episode_counts(&episodes);
+
+        // Remove all episodes that haven't aired yet
+        episodes.retain(|episode| match episode.airstamp {
+            Some(airstamp) => Utc::now() >= airstamp,
+            None => false,
+        });
+
+        // Remove all episodes that have already been watched
+        {
+            let mut index = 0;
+            let mut current_show = 0;
+            let subscribed_shows = self.user_data.subscribed_shows();
+            episodes.retain(|episode| {
+                if episode.show_id != current_show {
+                    index = match subscribed_shows
+                        .iter()
+                        .position(|show| show.id == episode.show_id)
+                    {
+                        Some(index) => index,
+                        None => return false,
+                    };
+                    current_show = episode.show_id;
+                }
+
+                let last_watched = subscribed_shows[index].last_watched_episode;
+                if episode.season == last_watched.0 {
+                    episode.number > last_watched.1
+                } else {
+                    episode.season > last_watched.0
+                }
+            });
+        }
+
+        // Update user data
+        // TODO: maybe store both id and (season, number) in last_watched_episode field?
         //       This way, one could detect if episode number for given id ever changes..
-        episodes.retain(|episode| !self.user_data.update_episode(episode));
+        episodes.retain(|episode| !self.user_data.update_episode(episode, &mut changes));
+
+        if self.verbose {
+            for change in &changes {
+                println!("{}", change);
+            }
+        }
 
         // Add new episodes
+        let new_episodes = episodes.len();
         if !episodes.is_empty() {
-            {
+            if self.verbose {
                 let mut show_names: HashMap<usize, &str> = HashMap::new();
                 let shows = self.user_data.subscribed_shows();
                 for show in shows {
@@ -650,6 +2274,7 @@ impl App {
                     &episodes,
                     &HorizontalSeparator::Week,
                     Some(&show_names),
+                    None,
                 );
                 println!();
             }
@@ -659,6 +2284,106 @@ impl App {
 
         self.user_data.store()?;
 
+        Ok((new_episodes, changes))
+    }
+
+    /// Send a digest of new and upcoming episodes and status changes by email.
+    ///
+    /// Newly aired episodes are taken from the unwatched episodes, so `update` should be run
+    /// beforehand. Upcoming episodes are fetched from the web API. With a tag, other shows are
+    /// left out, but the digest still counts as sent for them.
+    pub fn digest(
+        &mut self,
+        smtp_server: &str,
+        from: &str,
+        to: &str,
+        tag: Option<&str>,
+    ) -> Result<()> {
+        let now = Utc::now();
+        let since = match self.user_data.last_digest() {
+            Some(last_digest) => last_digest,
+            None => now - Duration::days(1),
+        };
+
+        let tagged_shows: Vec<&Show> = self
+            .user_data
+            .subscribed_shows()
+            .iter()
+            .filter(|show| show.matches_tag(tag))
+            .collect();
+
+        // Episodes of paused or dropped shows are left out, but their status changes aren't
+        let show_ids: Vec<usize> = tagged_shows
+            .iter()
+            .filter(|show| !show.viewing_state.is_inactive())
+            .map(|show| show.id)
+            .collect();
+
+        // Get episodes airing within the next week
+
+        let mut upcoming_episodes = if show_ids.is_empty() {
+            Vec::new()
+        } else {
+            self.api.get_episodes_sequential(&show_ids)?
+        };
+
+        if self.verbose && !show_ids.is_empty() {
+            println!();
+        }
+
+        upcoming_episodes.retain(|episode| match episode.airstamp {
+            Some(airstamp) => airstamp > now && airstamp <= now + Duration::weeks(1),
+            None => false,
+        });
+        upcoming_episodes.sort_by_key(|episode| episode.airstamp);
+
+        {
+            let mut show_names: HashMap<usize, &str> = HashMap::new();
+            let shows = self.user_data.subscribed_shows();
+            for show in shows {
+                show_names.insert(show.id, &show.name);
+            }
+
+            let mut new_episodes = self.user_data.new_episodes_since_digest(since);
+            new_episodes.retain(|episode| show_ids.contains(&episode.show_id));
+            let mut status_changes = self.user_data.status_changes_since(Some(since));
+            status_changes
+                .retain(|change| tagged_shows.iter().any(|show| show.id == change.show_id));
+
+            let digest = Digest {
+                new_episodes,
+                upcoming_episodes: upcoming_episodes.iter().collect(),
+                status_changes,
+                show_names: &show_names,
+            };
+
+            if digest.is_empty() {
+                println!("Nothing new since the last digest, no email sent.");
+                return Ok(());
+            }
+
+            let subject = format!("bingers digest for {}", now.format("%a, %b %d, %Y"));
+            let text = digest.to_text();
+            let html = digest.to_html();
+
+            smtp::send_mail(
+                smtp_server,
+                &Mail {
+                    from,
+                    to,
+                    subject: &subject,
+                    text: &text,
+                    html: &html,
+                },
+            )
+            .chain_err(|| format!("Unable to send digest to {}", to))?;
+
+            println!("Sent digest to {}.", to);
+        }
+
+        self.user_data.set_last_digest(now);
+        self.user_data.store()?;
+
         Ok(())
     }
 }
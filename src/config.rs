@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use errors::*;
+use user_data;
+
+const CONFIG_FILE: &str = "config.json";
+
+/// Remote file to synchronize the user data with, e.g.
+/// `https://cloud.example.com/remote.php/dav/files/me/bingers/user_data.json`.
+#[derive(Debug, Deserialize)]
+pub struct WebDavConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Jellyfin (or Emby) server to read the played status of episodes from.
+#[derive(Debug, Deserialize)]
+pub struct JellyfinConfig {
+    pub url: String,
+    pub api_key: String,
+    /// Name of the user, can be omitted if the server has a single user
+    pub user: Option<String>,
+}
+
+/// Settings from `config.json` in the data directory. All settings are optional.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub webdav: Option<WebDavConfig>,
+    #[serde(default)]
+    pub jellyfin: Option<JellyfinConfig>,
+}
+
+impl Config {
+    pub fn path() -> Result<PathBuf> {
+        Ok(user_data::data_dir()?.join(CONFIG_FILE))
+    }
+
+    /// Loads the configuration. Returns the default configuration if there is no config file.
+    pub fn load() -> Result<Self> {
+        let path = Config::path()?;
+
+        let mut json = String::new();
+        match File::open(&path).and_then(|mut file| file.read_to_string(&mut json)) {
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => {
+                return Err(Error::from(e)).chain_err(|| format!("Unable to read {:?}", path))
+            }
+        }
+
+        ::serde_json::from_str(&json).chain_err(|| format!("Unable to parse {:?}", path))
+    }
+}
@@ -0,0 +1,235 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{self, DateTime, Utc};
+use error_chain::ChainedError;
+use rand::{self, Rng};
+use signal_hook;
+
+use app::App;
+use errors::*;
+use user_data;
+
+const STATUS_FILE: &str = "daemon_status.json";
+const TRIGGER_FILE: &str = "daemon.trigger";
+
+/// Parses a duration such as "45s", "90m", "6h" or "1d".
+pub fn parse_duration(duration: &str) -> Result<Duration> {
+    let duration = duration.trim();
+    let (value, unit) = duration.split_at(
+        duration
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(duration.len()),
+    );
+
+    let value: u64 = value
+        .parse()
+        .chain_err(|| format!("Invalid duration [{}]", duration))?;
+
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" | "" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => bail!(
+            "Invalid duration unit [{}], expected one of s, m, h, d",
+            unit
+        ),
+    };
+
+    match value.checked_mul(unit_seconds) {
+        Some(seconds) => Ok(Duration::from_secs(seconds)),
+        None => bail!("Duration [{}] is too long", duration),
+    }
+}
+
+pub struct DaemonOptions {
+    pub interval: Duration,
+    pub jitter: Duration,
+    pub hook: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DaemonStatus {
+    pid: u32,
+    state: &'static str,
+    started: DateTime<Utc>,
+    last_update: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    new_episodes: usize,
+    next_update: Option<DateTime<Utc>>,
+}
+
+impl DaemonStatus {
+    fn store(&self, data_dir: &Path) -> Result<()> {
+        let status_tmp = data_dir.join("daemon_status.tmp");
+        let status_file = data_dir.join(STATUS_FILE);
+
+        fs::create_dir_all(data_dir)
+            .chain_err(|| format!("Unable to create user data directory {:?}", data_dir))?;
+
+        let json = ::serde_json::to_string_pretty(self)
+            .chain_err(|| "Unable to serialize daemon status.")?;
+
+        File::create(&status_tmp)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
+            .chain_err(|| format!("Unable to write daemon status to {:?}", status_tmp))?;
+
+        fs::rename(&status_tmp, &status_file)
+            .chain_err(|| format!("Unable to move {:?} to {:?}", status_tmp, status_file))?;
+
+        Ok(())
+    }
+}
+
+fn log(message: &str) {
+    println!("[{}] {}", Utc::now().format("%Y-%m-%d %H:%M:%S"), message);
+}
+
+fn run_hook(hook: &str, new_episodes: usize) {
+    let result = Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .env("BINGERS_NEW_EPISODES", new_episodes.to_string())
+        .status();
+
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => log(&format!("Hook exited with {}", status)),
+        Err(e) => log(&format!("Unable to run hook: {}", e)),
+    }
+}
+
+fn trigger_file() -> Result<PathBuf> {
+    Ok(user_data::data_dir()?.join(TRIGGER_FILE))
+}
+
+/// Asks a running daemon to update immediately.
+pub fn trigger_refresh() -> Result<()> {
+    let trigger_file = trigger_file()?;
+
+    File::create(&trigger_file)
+        .chain_err(|| format!("Unable to create trigger file {:?}", trigger_file))?;
+
+    Ok(())
+}
+
+/// Periodically updates shows and episodes until SIGTERM or SIGINT is received.
+///
+/// An immediate update can be requested with SIGUSR1 or with `trigger_refresh()`. The user
+/// data is reloaded before every update, as it may have been changed by other commands.
+pub fn run(app: &mut App, options: &DaemonOptions) -> Result<()> {
+    if options.interval == Duration::from_secs(0) {
+        bail!("The update interval must be longer than zero");
+    }
+
+    let terminate = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::SIGTERM, Arc::clone(&terminate))?;
+    signal_hook::flag::register(signal_hook::SIGINT, Arc::clone(&terminate))?;
+
+    let refresh = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::SIGUSR1, Arc::clone(&refresh))?;
+
+    let data_dir = user_data::data_dir()?;
+    let trigger_file = trigger_file()?;
+
+    let mut status = DaemonStatus {
+        pid: process::id(),
+        state: "starting",
+        started: Utc::now(),
+        last_update: None,
+        last_error: None,
+        new_episodes: 0,
+        next_update: None,
+    };
+
+    while !terminate.load(Ordering::SeqCst) {
+        // Update shows and episodes
+        log("Updating...");
+        status.state = "updating";
+        status.store(&data_dir)?;
+
+        // Start from the stored user data, which may have been changed in the meantime
+        match app.reload().and_then(|_| app.update(false, None)) {
+            Ok(new_episodes) => {
+                log(&format!(
+                    "Update finished, {} new episode(s).",
+                    new_episodes
+                ));
+                status.last_error = None;
+                status.new_episodes = new_episodes;
+
+                if new_episodes > 0 {
+                    if let Some(ref hook) = options.hook {
+                        run_hook(hook, new_episodes);
+                    }
+                }
+            }
+            Err(e) => {
+                log(&format!("Update failed: {}", e.display_chain()));
+                status.last_error = Some(e.to_string());
+            }
+        }
+        status.last_update = Some(Utc::now());
+
+        // Schedule next update
+        let jitter = if options.jitter.as_secs() > 0 {
+            Duration::from_secs(rand::thread_rng().gen_range(0, options.jitter.as_secs() + 1))
+        } else {
+            Duration::from_secs(0)
+        };
+        let delay = options.interval + jitter;
+
+        status.state = "idle";
+        status.next_update = Some(
+            Utc::now()
+                + chrono::Duration::from_std(delay).chain_err(|| "Invalid update interval")?,
+        );
+        status.store(&data_dir)?;
+
+        // Wait until next update is due or a refresh is triggered
+        let deadline = Instant::now() + delay;
+        while Instant::now() < deadline
+            && !terminate.load(Ordering::SeqCst)
+            && !refresh.swap(false, Ordering::SeqCst)
+        {
+            if trigger_file.exists() {
+                let _ = fs::remove_file(&trigger_file);
+                break;
+            }
+
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+
+    log("Shutting down...");
+
+    status.state = "stopped";
+    status.next_update = None;
+    status.store(&data_dir)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(Duration::from_secs(45), parse_duration("45s").unwrap());
+        assert_eq!(Duration::from_secs(90 * 60), parse_duration("90m").unwrap());
+        assert_eq!(Duration::from_secs(90 * 60), parse_duration("90").unwrap());
+        assert_eq!(Duration::from_secs(6 * 3600), parse_duration("6h").unwrap());
+        assert_eq!(Duration::from_secs(86400), parse_duration("1d").unwrap());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("6 weeks").is_err());
+        assert!(parse_duration("18446744073709551615d").is_err());
+    }
+}
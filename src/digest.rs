@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use app::{App, HorizontalSeparator};
+use tvmaze_api::Episode;
+use user_data::StatusChange;
+
+/// Contents of a digest email.
+pub struct Digest<'a> {
+    pub new_episodes: Vec<&'a Episode>,
+    pub upcoming_episodes: Vec<&'a Episode>,
+    pub status_changes: Vec<&'a StatusChange>,
+    pub show_names: &'a HashMap<usize, &'a str>,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+impl<'a> Digest<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.new_episodes.is_empty()
+            && self.upcoming_episodes.is_empty()
+            && self.status_changes.is_empty()
+    }
+
+    fn show_name(&self, show_id: usize) -> &str {
+        match self.show_names.get(&show_id) {
+            Some(name) => name,
+            None => "???",
+        }
+    }
+
+    fn status_change_description(&self, change: &StatusChange) -> String {
+        format!(
+            "{}: Changed from {} to {}",
+            self.show_name(change.show_id),
+            change.previous,
+            change.current
+        )
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for &(title, episodes) in &[
+            ("New episodes", &self.new_episodes),
+            ("Upcoming episodes this week", &self.upcoming_episodes),
+        ] {
+            if episodes.is_empty() {
+                continue;
+            }
+
+            text.push_str(&format!("{}:\n\n", title));
+            text.push_str(&App::format_episode_list_as_table(
+                episodes,
+                &HorizontalSeparator::Week,
+                Some(self.show_names),
+                None,
+            ));
+            text.push('\n');
+        }
+
+        if !self.status_changes.is_empty() {
+            text.push_str("Status changes:\n\n");
+            for change in &self.status_changes {
+                text.push_str(&self.status_change_description(change));
+                text.push('\n');
+            }
+            text.push('\n');
+        }
+
+        text.push_str("Data provided by TVmaze.com\n");
+
+        text
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+
+        html.push_str("<html>\n<body>\n");
+
+        for &(title, episodes) in &[
+            ("New episodes", &self.new_episodes),
+            ("Upcoming episodes this week", &self.upcoming_episodes),
+        ] {
+            if episodes.is_empty() {
+                continue;
+            }
+
+            html.push_str(&format!("<h2>{}</h2>\n", title));
+            html.push_str("<table>\n");
+            html.push_str(
+                "<tr><th>Show</th><th>Season</th><th>Episode</th><th>Name</th><th>Air Date</th></tr>\n",
+            );
+
+            for episode in episodes.iter() {
+                let air_date = match episode.airstamp {
+                    Some(airstamp) => format!("{}", airstamp.format("%a, %b %d, %Y")),
+                    None => "TBD".to_string(),
+                };
+
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(self.show_name(episode.show_id)),
+                    episode.season,
+                    episode.number,
+                    escape_html(&episode.name),
+                    air_date
+                ));
+            }
+
+            html.push_str("</table>\n");
+        }
+
+        if !self.status_changes.is_empty() {
+            html.push_str("<h2>Status changes</h2>\n<ul>\n");
+            for change in &self.status_changes {
+                html.push_str(&format!(
+                    "<li>{}</li>\n",
+                    escape_html(&self.status_change_description(change))
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+
+        html.push_str("<p>Data provided by TVmaze.com</p>\n");
+        html.push_str("</body>\n</html>\n");
+
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{TimeZone, Utc};
+
+    use tvmaze_api::Status;
+
+    fn episode() -> Episode {
+        Episode {
+            episode_id: 1201557,
+            show_id: 20263,
+            name: "About a <Girl>".to_string(),
+            season: 1,
+            number: 3,
+            airstamp: Some(Utc.ymd(2017, 9, 22).and_hms(1, 0, 0)),
+            runtime: Some(60),
+            watched: false,
+        }
+    }
+
+    #[test]
+    fn escape_special_characters() {
+        assert_eq!(
+            "Law &amp; Order: &lt;&quot;SVU&quot;&gt;",
+            escape_html("Law & Order: <\"SVU\">")
+        );
+        assert_eq!("Grey's Anatomy", escape_html("Grey's Anatomy"));
+    }
+
+    #[test]
+    fn format_digest() {
+        let episode = episode();
+        let change = StatusChange {
+            show_id: 20263,
+            previous: Status::Running,
+            current: Status::Ended,
+            changed: Utc.ymd(2017, 9, 23).and_hms(0, 0, 0),
+        };
+        let mut show_names = HashMap::new();
+        show_names.insert(20263, "The Orville");
+
+        let digest = Digest {
+            new_episodes: vec![&episode],
+            upcoming_episodes: Vec::new(),
+            status_changes: vec![&change],
+            show_names: &show_names,
+        };
+        assert!(!digest.is_empty());
+
+        let text = digest.to_text();
+        assert!(text.starts_with("New episodes:\n\n"));
+        assert!(text.contains("About a <Girl>"));
+        assert!(!text.contains("Upcoming episodes"));
+        assert!(text.contains("Status changes:\n\nThe Orville: Changed from Running to Ended\n"));
+
+        let html = digest.to_html();
+        assert!(html.contains("<h2>New episodes</h2>"));
+        assert!(html.contains(
+            "<tr><td>The Orville</td><td>1</td><td>3</td><td>About a &lt;Girl&gt;</td>\
+             <td>Fri, Sep 22, 2017</td></tr>"
+        ));
+        assert!(!html.contains("Upcoming episodes"));
+        assert!(html.contains("<li>The Orville: Changed from Running to Ended</li>"));
+
+        let empty = Digest {
+            new_episodes: Vec::new(),
+            upcoming_episodes: Vec::new(),
+            status_changes: Vec::new(),
+            show_names: &show_names,
+        };
+        assert!(empty.is_empty());
+    }
+}
@@ -14,6 +14,7 @@ error_chain! {
         AppDirsError(::app_dirs::AppDirsError);
         ParseIntError(::std::num::ParseIntError);
         TokioTimerError(::tokio_timer::Error);
+        ReadlineError(::rustyline::error::ReadlineError);
     }
 
     errors {
@@ -26,6 +27,16 @@ error_chain! {
             description("User data version mismatch"),
             display("User data version mismatch [Expected: < {}, actual: {}]", expected, actual),
         }
+
+        SmtpError(reply: String) {
+            description("SMTP error"),
+            display("SMTP error: Server replied with \"{}\"", reply),
+        }
+
+        GitError(command: String, output: String) {
+            description("git error"),
+            display("git {} failed: {}", command, output),
+        }
     }
 }
 
@@ -0,0 +1,491 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use errors::*;
+use tvmaze_api::{Episode, Externals, Network, Schedule, Show, Status, ViewingState};
+use user_data::{UserData, WatchedEpisode};
+
+/// Version of the portable export format, see `docs/export-format.md`.
+///
+/// Increment on incompatible changes. Fields can be added without a new version, as long as
+/// they have a default value.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Show together with its unwatched episodes.
+pub type ShowWithEpisodes = (Show, Vec<Episode>);
+
+/// Portable export of the user data, independent of the internal representation.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Export {
+    pub version: u32,
+    pub exported: DateTime<Utc>,
+    pub shows: Vec<ExportedShow>,
+    pub history: Vec<ExportedHistoryEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportedEpisodeNumber {
+    pub season: usize,
+    pub number: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportedShow {
+    pub tvmaze_id: usize,
+    pub name: String,
+    pub language: Option<String>,
+    pub network: Option<Network>,
+    pub web_channel: Option<Network>,
+    pub status: Status,
+    pub runtime: Option<usize>,
+    #[serde(default)]
+    pub premiered: Option<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+    #[serde(default)]
+    pub externals: Externals,
+    pub last_watched: Option<ExportedEpisodeNumber>,
+    pub viewing_state: String,
+    #[serde(default)]
+    pub dropped_reason: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: Vec<String>,
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub unwatched_episodes: Vec<ExportedEpisode>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportedEpisode {
+    pub tvmaze_id: usize,
+    pub season: usize,
+    pub number: usize,
+    pub name: String,
+    pub airstamp: Option<DateTime<Utc>>,
+    pub runtime: Option<usize>,
+    pub watched: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportedHistoryEntry {
+    pub show_tvmaze_id: usize,
+    pub episode_tvmaze_id: usize,
+    pub season: usize,
+    pub number: usize,
+    pub name: String,
+    pub runtime: Option<usize>,
+    pub watched_at: DateTime<Utc>,
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+fn viewing_state_name(viewing_state: &ViewingState) -> &'static str {
+    match *viewing_state {
+        ViewingState::Watching => "watching",
+        ViewingState::Paused => "paused",
+        ViewingState::Dropped(_) => "dropped",
+        ViewingState::Completed => "completed",
+        ViewingState::PlanToWatch => "plan-to-watch",
+    }
+}
+
+impl ExportedShow {
+    fn new(show: &Show, episodes: &[Episode]) -> Self {
+        let (season, number) = show.last_watched_episode;
+
+        Self {
+            tvmaze_id: show.id,
+            name: show.name.clone(),
+            language: show.language.clone(),
+            network: show.network.clone(),
+            web_channel: show.web_channel.clone(),
+            status: show.status.clone(),
+            runtime: show.runtime,
+            premiered: show.premiered.clone(),
+            genres: show.genres.clone(),
+            schedule: Some(show.schedule.clone()),
+            externals: show.externals.clone(),
+            last_watched: if (season, number) == (0, 0) {
+                None
+            } else {
+                Some(ExportedEpisodeNumber { season, number })
+            },
+            viewing_state: viewing_state_name(&show.viewing_state).to_string(),
+            dropped_reason: match show.viewing_state {
+                ViewingState::Dropped(ref reason) => reason.clone(),
+                _ => None,
+            },
+            tags: show.tags.clone(),
+            notes: show.notes.clone(),
+            fields: show.fields.clone(),
+            priority: show.priority,
+            unwatched_episodes: episodes
+                .iter()
+                .filter(|episode| episode.show_id == show.id)
+                .map(|episode| ExportedEpisode {
+                    tvmaze_id: episode.episode_id,
+                    season: episode.season,
+                    number: episode.number,
+                    name: episode.name.clone(),
+                    airstamp: episode.airstamp,
+                    runtime: episode.runtime,
+                    watched: episode.watched,
+                })
+                .collect(),
+        }
+    }
+
+    /// Converts the exported show back into a show and its unwatched episodes.
+    ///
+    /// The show is marked as outdated, so that the next update refreshes all information
+    /// provided by TvMaze.com.
+    pub fn into_show(self) -> Result<ShowWithEpisodes> {
+        let viewing_state = match self.viewing_state.as_str() {
+            "watching" => ViewingState::Watching,
+            "paused" => ViewingState::Paused,
+            "dropped" => ViewingState::Dropped(self.dropped_reason),
+            "completed" => ViewingState::Completed,
+            "plan-to-watch" => ViewingState::PlanToWatch,
+            state => bail!("Invalid viewing state [{}] of {}", state, self.name),
+        };
+
+        let show_id = self.tvmaze_id;
+        let episodes = self
+            .unwatched_episodes
+            .into_iter()
+            .map(|episode| Episode {
+                episode_id: episode.tvmaze_id,
+                show_id,
+                name: episode.name,
+                season: episode.season,
+                number: episode.number,
+                airstamp: episode.airstamp,
+                runtime: episode.runtime,
+                watched: episode.watched,
+            })
+            .collect();
+
+        let show = Show {
+            id: show_id,
+            name: self.name,
+            language: self.language,
+            network: self.network,
+            web_channel: self.web_channel,
+            status: self.status,
+            runtime: self.runtime,
+            premiered: self.premiered,
+            genres: self.genres,
+            summary: None,
+            externals: self.externals,
+            schedule: self.schedule.unwrap_or(Schedule {
+                time: String::new(),
+                days: Vec::new(),
+            }),
+            last_updated: 0,
+            last_watched_episode: self.last_watched.map_or((0, 0), |last_watched| {
+                (last_watched.season, last_watched.number)
+            }),
+            viewing_state,
+            tags: self.tags,
+            notes: self.notes,
+            fields: self.fields,
+            priority: self.priority,
+            episode_counts: BTreeMap::new(),
+        };
+
+        Ok((show, episodes))
+    }
+}
+
+impl Export {
+    pub fn new(user_data: &UserData) -> Self {
+        let episodes = user_data.unwatched_episodes();
+
+        Self {
+            version: FORMAT_VERSION,
+            exported: Utc::now(),
+            shows: user_data
+                .subscribed_shows()
+                .iter()
+                .map(|show| ExportedShow::new(show, episodes))
+                .collect(),
+            history: user_data
+                .history()
+                .iter()
+                .map(|entry| ExportedHistoryEntry {
+                    show_tvmaze_id: entry.show_id,
+                    episode_tvmaze_id: entry.episode_id,
+                    season: entry.season,
+                    number: entry.number,
+                    name: entry.name.clone(),
+                    runtime: entry.runtime,
+                    watched_at: entry.watched,
+                    rating: entry.rating,
+                    note: entry.note.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Parses an export, rejecting versions of the format that are newer than this one.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let export: Export =
+            ::serde_json::from_str(json).chain_err(|| "Unable to parse bingers export")?;
+
+        if export.version > FORMAT_VERSION {
+            bail!(
+                "Unsupported export format version [Supported: <= {}, actual: {}]",
+                FORMAT_VERSION,
+                export.version
+            );
+        }
+
+        Ok(export)
+    }
+
+    /// Converts the export back into shows with their unwatched episodes and the history.
+    pub fn into_user_data(self) -> Result<(Vec<ShowWithEpisodes>, Vec<WatchedEpisode>)> {
+        let shows = self
+            .shows
+            .into_iter()
+            .map(ExportedShow::into_show)
+            .collect::<Result<Vec<_>>>()?;
+
+        let history = self
+            .history
+            .into_iter()
+            .map(|entry| WatchedEpisode {
+                episode_id: entry.episode_tvmaze_id,
+                show_id: entry.show_tvmaze_id,
+                season: entry.season,
+                number: entry.number,
+                name: entry.name,
+                runtime: entry.runtime,
+                watched: entry.watched_at,
+                rating: entry.rating,
+                note: entry.note,
+            })
+            .collect();
+
+        Ok((shows, history))
+    }
+}
+
+/// Ids identifying a show on Trakt. Missing ids are left out.
+#[derive(Debug, Serialize)]
+pub struct TraktIds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tvdb: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imdb: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TraktHistoryEpisode {
+    pub number: usize,
+    pub watched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TraktHistorySeason {
+    pub number: usize,
+    pub episodes: Vec<TraktHistoryEpisode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TraktHistoryShow {
+    pub title: String,
+    pub ids: TraktIds,
+    pub seasons: Vec<TraktHistorySeason>,
+}
+
+/// Watch history in the format of the body of Trakt's history sync (`POST /sync/history`).
+#[derive(Debug, Serialize)]
+pub struct TraktHistory {
+    pub shows: Vec<TraktHistoryShow>,
+}
+
+impl TraktHistory {
+    /// Converts the history, identifying episodes by the TheTVDB or IMDb id of their show
+    /// together with season and number.
+    ///
+    /// Trakt can't identify episodes of shows without these ids, and the ids of shows that
+    /// are no longer subscribed are unknown. Their history entries are left out, their number
+    /// is returned together with the history.
+    pub fn new(user_data: &UserData) -> (Self, usize) {
+        let mut shows: Vec<(usize, TraktHistoryShow)> = Vec::new();
+        let mut left_out = 0;
+
+        for entry in user_data.history() {
+            let index = match shows.iter().position(|&(id, _)| id == entry.show_id) {
+                Some(index) => index,
+                None => {
+                    let show = user_data
+                        .subscribed_shows()
+                        .iter()
+                        .find(|show| show.id == entry.show_id)
+                        .filter(|show| {
+                            show.externals.thetvdb.is_some() || show.externals.imdb.is_some()
+                        });
+                    match show {
+                        Some(show) => {
+                            shows.push((
+                                show.id,
+                                TraktHistoryShow {
+                                    title: show.name.clone(),
+                                    ids: TraktIds {
+                                        tvdb: show.externals.thetvdb,
+                                        imdb: show.externals.imdb.clone(),
+                                    },
+                                    seasons: Vec::new(),
+                                },
+                            ));
+                            shows.len() - 1
+                        }
+                        None => {
+                            left_out += 1;
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            let seasons = &mut shows[index].1.seasons;
+            let season = match seasons
+                .iter()
+                .position(|season| season.number == entry.season)
+            {
+                Some(season) => season,
+                None => {
+                    seasons.push(TraktHistorySeason {
+                        number: entry.season,
+                        episodes: Vec::new(),
+                    });
+                    seasons.len() - 1
+                }
+            };
+            seasons[season].episodes.push(TraktHistoryEpisode {
+                number: entry.number,
+                watched_at: entry.watched,
+            });
+        }
+
+        let history = TraktHistory {
+            shows: shows.into_iter().map(|(_, show)| show).collect(),
+        };
+
+        (history, left_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use user_data::tests::show_json;
+
+    #[test]
+    fn convert_show_back_and_forth() {
+        let mut show: Show = ::serde_json::from_str(
+            r#"{"id": 20263, "name": "The Orville", "language": "English",
+                "network": {"id": 4, "name": "FOX"}, "webChannel": null, "status": "Running",
+                "runtime": 60, "schedule": {"time": "21:00", "days": ["Thursday"]},
+                "updated": 1573667713, "externals": {"tvrage": null, "thetvdb": 328487,
+                "imdb": "tt5691552"}}"#,
+        )
+        .unwrap();
+        show.last_watched_episode = (1, 2);
+        show.viewing_state = ViewingState::Dropped(Some("too long".to_string()));
+        show.tags = vec!["scifi".to_string()];
+        let episode: Episode = ::serde_json::from_str(
+            r#"{"id": 1201557, "name": "About a Girl", "season": 1, "number": 3,
+                "airstamp": "2017-09-22T01:00:00+00:00", "runtime": 60}"#,
+        )
+        .map(|mut episode: Episode| {
+            episode.show_id = 20263;
+            episode
+        })
+        .unwrap();
+
+        let json = ::serde_json::to_string(&ExportedShow::new(&show, &[episode])).unwrap();
+        let exported: ExportedShow = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(2, exported.last_watched.as_ref().unwrap().number);
+        assert_eq!("dropped", exported.viewing_state);
+
+        let (imported_show, episodes) = exported.into_show().unwrap();
+        assert_eq!((1, 2), imported_show.last_watched_episode);
+        assert_eq!(show.viewing_state, imported_show.viewing_state);
+        assert_eq!(show.tags, imported_show.tags);
+        assert_eq!(show.externals, imported_show.externals);
+        assert_eq!(0, imported_show.last_updated);
+        assert_eq!(1, episodes.len());
+        assert_eq!(
+            (20263, 1201557),
+            (episodes[0].show_id, episodes[0].episode_id)
+        );
+    }
+
+    #[test]
+    fn reject_newer_format_version() {
+        let json = format!(
+            r#"{{"version": {}, "exported": "2026-10-18T12:00:00Z", "shows": [], "history": []}}"#,
+            FORMAT_VERSION + 1
+        );
+        assert!(Export::from_json(&json).is_err());
+
+        let json = json.replace(
+            &format!("\"version\": {}", FORMAT_VERSION + 1),
+            "\"version\": 1",
+        );
+        assert!(Export::from_json(&json).is_ok());
+    }
+
+    #[test]
+    fn convert_history_to_trakt() {
+        let the_orville = show_json(
+            20263,
+            "The Orville",
+            r#""externals": {"tvrage": null, "thetvdb": 328487, "imdb": null}"#,
+        );
+        let json = format!(
+            r#"{{"version": 1, "unwatched_episodes": [], "subscribed_shows": [{}],
+                "history": [
+                    {{"episode_id": 1, "show_id": 20263, "season": 1, "number": 1, "name": "A",
+                     "runtime": 60, "watched": "2026-03-01T20:00:00Z"}},
+                    {{"episode_id": 2, "show_id": 82, "season": 1, "number": 1, "name": "B",
+                     "runtime": 60, "watched": "2026-03-02T20:00:00Z"}},
+                    {{"episode_id": 3, "show_id": 20263, "season": 2, "number": 1, "name": "C",
+                     "runtime": 60, "watched": "2026-03-03T20:00:00Z"}},
+                    {{"episode_id": 4, "show_id": 20263, "season": 1, "number": 2, "name": "D",
+                     "runtime": 60, "watched": "2026-03-04T20:00:00Z"}}]}}"#,
+            the_orville
+        );
+        let user_data = UserData::from_json(::std::path::PathBuf::new(), &json).unwrap();
+
+        let (history, left_out) = TraktHistory::new(&user_data);
+        assert_eq!(1, left_out);
+        assert_eq!(
+            ::serde_json::json!({"shows": [{
+                "title": "The Orville",
+                "ids": {"tvdb": 328487},
+                "seasons": [
+                    {"number": 1, "episodes": [
+                        {"number": 1, "watched_at": "2026-03-01T20:00:00Z"},
+                        {"number": 2, "watched_at": "2026-03-04T20:00:00Z"}]},
+                    {"number": 2, "episodes": [
+                        {"number": 1, "watched_at": "2026-03-03T20:00:00Z"}]}]
+            }]}),
+            ::serde_json::to_value(&history).unwrap()
+        );
+    }
+}
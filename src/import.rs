@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+
+use errors::*;
+
+/// Watched episode of an imported show.
+pub struct ImportedEpisode {
+    pub season: usize,
+    pub number: usize,
+    pub watched: Option<DateTime<Utc>>,
+}
+
+/// Show with watched episodes, as exported by another service.
+pub struct ImportedShow {
+    pub title: String,
+    pub thetvdb: Option<usize>,
+    pub imdb: Option<String>,
+    pub episodes: Vec<ImportedEpisode>,
+}
+
+#[derive(Deserialize)]
+struct TraktIds {
+    tvdb: Option<usize>,
+    imdb: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TraktShow {
+    title: String,
+    ids: TraktIds,
+}
+
+#[derive(Deserialize)]
+struct TraktEpisode {
+    number: usize,
+    last_watched_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct TraktSeason {
+    number: usize,
+    episodes: Vec<TraktEpisode>,
+}
+
+#[derive(Deserialize)]
+struct TraktWatchedShow {
+    show: TraktShow,
+    #[serde(default)]
+    seasons: Vec<TraktSeason>,
+}
+
+fn parse_trakt_watched_shows(json: &str) -> Result<Vec<ImportedShow>> {
+    let watched_shows: Vec<TraktWatchedShow> =
+        ::serde_json::from_str(json).chain_err(|| "Unable to parse Trakt export")?;
+
+    Ok(watched_shows
+        .into_iter()
+        .map(|watched_show| ImportedShow {
+            title: watched_show.show.title,
+            thetvdb: watched_show.show.ids.tvdb,
+            imdb: watched_show.show.ids.imdb,
+            episodes: watched_show
+                .seasons
+                .iter()
+                .flat_map(|season| {
+                    season.episodes.iter().map(move |episode| ImportedEpisode {
+                        season: season.number,
+                        number: episode.number,
+                        watched: episode.last_watched_at,
+                    })
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+/// Reads the watched shows of a Trakt export, given either the export directory or the
+/// `watched-shows.json` file itself.
+pub fn read_trakt_export(path: &Path) -> Result<Vec<ImportedShow>> {
+    let path = if path.is_dir() {
+        path.join("watched-shows.json")
+    } else {
+        path.to_path_buf()
+    };
+
+    let mut json = String::new();
+    File::open(&path)
+        .and_then(|mut file| file.read_to_string(&mut json))
+        .chain_err(|| format!("Unable to read [{}]", path.display()))?;
+
+    parse_trakt_watched_shows(&json)
+}
+
+/// Splits CSV text into records of fields. Fields may be quoted, quoted fields may contain
+/// separators, line breaks and escaped quotes ("").
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => record.push(field.split_off(0)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(field.split_off(0));
+                records.push(record.split_off(0));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
+
+/// Parses CSV text with a header into maps from column name to value.
+fn parse_csv_records(text: &str) -> Vec<HashMap<String, String>> {
+    let mut records = parse_csv(text.trim_start_matches('\u{feff}')).into_iter();
+    let header = records.next().unwrap_or_default();
+
+    records
+        .map(|record| header.iter().cloned().zip(record).collect())
+        .collect()
+}
+
+fn read_csv(path: &Path) -> Result<Vec<HashMap<String, String>>> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .chain_err(|| format!("Unable to read [{}]", path.display()))?;
+
+    Ok(parse_csv_records(&text))
+}
+
+fn parse_tvtime_export(
+    followed_shows: &[HashMap<String, String>],
+    seen_episodes: &[HashMap<String, String>],
+) -> Vec<ImportedShow> {
+    let mut shows: Vec<ImportedShow> = Vec::new();
+
+    // Shows are identified by their TheTVDB id, if the export contains it, otherwise by name
+    for record in followed_shows.iter().chain(seen_episodes) {
+        let title = match record.get("tv_show_name") {
+            Some(title) => title.trim(),
+            None => continue,
+        };
+        let thetvdb = record
+            .get("tv_show_id")
+            .and_then(|id| id.trim().parse::<usize>().ok());
+
+        let index = match shows.iter().position(|show| match (show.thetvdb, thetvdb) {
+            (Some(a), Some(b)) => a == b,
+            _ => show.title == title,
+        }) {
+            Some(index) => index,
+            None => {
+                shows.push(ImportedShow {
+                    title: title.to_string(),
+                    thetvdb,
+                    imdb: None,
+                    episodes: Vec::new(),
+                });
+                shows.len() - 1
+            }
+        };
+        let show = &mut shows[index];
+        if show.thetvdb.is_none() {
+            show.thetvdb = thetvdb;
+        }
+
+        let season = record
+            .get("episode_season_number")
+            .and_then(|season| season.trim().parse::<usize>().ok());
+        let number = record
+            .get("episode_number")
+            .and_then(|number| number.trim().parse::<usize>().ok());
+        if let (Some(season), Some(number)) = (season, number) {
+            if show
+                .episodes
+                .iter()
+                .any(|episode| (episode.season, episode.number) == (season, number))
+            {
+                continue;
+            }
+
+            let watched = record
+                .get("created_at")
+                .or_else(|| record.get("updated_at"))
+                .and_then(|watched| {
+                    NaiveDateTime::parse_from_str(watched.trim(), "%Y-%m-%d %H:%M:%S").ok()
+                })
+                .map(|watched| DateTime::from_utc(watched, Utc));
+
+            show.episodes.push(ImportedEpisode {
+                season,
+                number,
+                watched,
+            });
+        }
+    }
+
+    shows
+}
+
+/// Reads the followed shows and seen episodes of a TV Time (GDPR) export, given either the
+/// export directory or the `seen_episode.csv` file itself.
+pub fn read_tvtime_export(path: &Path) -> Result<Vec<ImportedShow>> {
+    let (followed_shows, seen_episodes) = if path.is_dir() {
+        let followed_shows = path.join("followed_tv_show.csv");
+        let followed_shows = if followed_shows.exists() {
+            read_csv(&followed_shows)?
+        } else {
+            Vec::new()
+        };
+
+        (followed_shows, read_csv(&path.join("seen_episode.csv"))?)
+    } else {
+        (Vec::new(), read_csv(path)?)
+    };
+
+    Ok(parse_tvtime_export(&followed_shows, &seen_episodes))
+}
+
+/// Played episodes with the title and unique ids of their show, from the video database of
+/// Kodi 17 or later.
+const KODI_PLAYED_EPISODES: &str = "
+    SELECT episode.idShow AS show_id, episode.strTitle AS title,
+           episode.c12 AS season, episode.c13 AS number, episode.lastPlayed AS last_played,
+           (SELECT value FROM uniqueid WHERE media_id = episode.idShow
+                AND media_type = 'tvshow' AND type = 'tvdb') AS tvdb,
+           (SELECT value FROM uniqueid WHERE media_id = episode.idShow
+                AND media_type = 'tvshow' AND type = 'imdb') AS imdb
+    FROM episode_view AS episode
+    WHERE episode.playCount > 0
+    ORDER BY episode.idShow";
+
+fn parse_kodi_episodes(records: &[HashMap<String, String>]) -> Vec<ImportedShow> {
+    let mut shows: Vec<(String, ImportedShow)> = Vec::new();
+
+    for record in records {
+        let field = |name: &str| {
+            record
+                .get(name)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+        let (show_id, title) = match (field("show_id"), field("title")) {
+            (Some(show_id), Some(title)) => (show_id, title),
+            _ => continue,
+        };
+
+        if shows.last().map(|(id, _)| id.as_str()) != Some(show_id) {
+            shows.push((
+                show_id.to_string(),
+                ImportedShow {
+                    title: title.to_string(),
+                    thetvdb: field("tvdb").and_then(|id| id.parse().ok()),
+                    imdb: field("imdb").map(|id| id.to_string()),
+                    episodes: Vec::new(),
+                },
+            ));
+        }
+
+        let season = field("season").and_then(|season| season.parse().ok());
+        let number = field("number").and_then(|number| number.parse().ok());
+        if let (Some(season), Some(number), Some(&mut (_, ref mut show))) =
+            (season, number, shows.last_mut())
+        {
+            // Kodi stores the local time
+            let watched = field("last_played")
+                .and_then(|watched| {
+                    NaiveDateTime::parse_from_str(watched, "%Y-%m-%d %H:%M:%S").ok()
+                })
+                .and_then(|watched| Local.from_local_datetime(&watched).earliest())
+                .map(|watched| watched.with_timezone(&Utc));
+
+            show.episodes.push(ImportedEpisode {
+                season,
+                number,
+                watched,
+            });
+        }
+    }
+
+    shows.into_iter().map(|(_, show)| show).collect()
+}
+
+/// Reads the played episodes of a Kodi video database (`MyVideosNNN.db`), using the sqlite3
+/// command line tool.
+pub fn read_kodi_database(path: &Path) -> Result<Vec<ImportedShow>> {
+    if !path.is_file() {
+        bail!("Kodi database [{}] not found", path.display());
+    }
+
+    let output = Command::new("sqlite3")
+        .arg("-readonly")
+        .arg("-csv")
+        .arg("-header")
+        .arg(path)
+        .arg(KODI_PLAYED_EPISODES)
+        .output()
+        .chain_err(|| "Unable to run sqlite3, is it installed?")?;
+
+    if !output.status.success() {
+        bail!(
+            "Unable to read Kodi database [{}]: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(parse_kodi_episodes(&parse_csv_records(
+        &String::from_utf8_lossy(&output.stdout),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_trakt_export() {
+        let shows = parse_trakt_watched_shows(
+            r#"[{"plays": 2, "last_watched_at": "2026-03-03T20:00:00.000Z",
+                "show": {"title": "The Orville", "year": 2017,
+                         "ids": {"trakt": 118164, "slug": "the-orville", "tvdb": 328487,
+                                 "imdb": "tt5691552", "tmdb": 71738, "tvrage": null}},
+                "seasons": [{"number": 1, "episodes": [
+                    {"number": 1, "plays": 1, "last_watched_at": "2026-03-02T20:00:00.000Z"},
+                    {"number": 3, "plays": 1, "last_watched_at": "2026-03-03T20:00:00.000Z"}]}]},
+               {"plays": 0, "show": {"title": "Unknown", "ids": {"trakt": 1}}}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(2, shows.len());
+        assert_eq!("The Orville", shows[0].title);
+        assert_eq!(Some(328487), shows[0].thetvdb);
+        assert_eq!(Some("tt5691552".to_string()), shows[0].imdb);
+        let episodes: Vec<(usize, usize)> = shows[0]
+            .episodes
+            .iter()
+            .map(|episode| (episode.season, episode.number))
+            .collect();
+        assert_eq!(vec![(1, 1), (1, 3)], episodes);
+        assert!(shows[0].episodes[0].watched.is_some());
+        assert_eq!(None, shows[1].thetvdb);
+        assert!(shows[1].episodes.is_empty());
+    }
+
+    #[test]
+    fn parse_quoted_csv() {
+        assert_eq!(
+            vec![
+                vec!["id", "name"],
+                vec!["1", "Law & Order, \"SVU\""],
+                vec!["2", "Line\nbreak"],
+            ],
+            parse_csv("id,name\r\n1,\"Law & Order, \"\"SVU\"\"\"\r\n2,\"Line\nbreak\"\r\n")
+        );
+    }
+
+    #[test]
+    fn parse_tvtime_export_by_id_and_name() {
+        let followed_shows =
+            parse_csv_records("tv_show_id,tv_show_name\n328487,The Orville\n1,Rome\n");
+        let seen_episodes = parse_csv_records(
+            "episode_id,tv_show_id,tv_show_name,episode_season_number,episode_number,created_at
+6219046,328487,The Orville,1,3,2026-03-03 20:00:00
+6219044,328487,The Orville,1,1,2026-03-02 20:00:00
+6219044,328487,The Orville,1,1,2026-03-04 20:00:00
+,,Severance,1,1,
+",
+        );
+
+        let shows = parse_tvtime_export(&followed_shows, &seen_episodes);
+        assert_eq!(3, shows.len());
+
+        assert_eq!(Some(328487), shows[0].thetvdb);
+        let episodes: Vec<(usize, usize)> = shows[0]
+            .episodes
+            .iter()
+            .map(|episode| (episode.season, episode.number))
+            .collect();
+        assert_eq!(vec![(1, 3), (1, 1)], episodes);
+        assert_eq!(
+            "2026-03-02T20:00:00Z".parse::<DateTime<Utc>>().ok(),
+            shows[0].episodes[1].watched
+        );
+
+        assert!(shows[1].episodes.is_empty());
+
+        assert_eq!("Severance", shows[2].title);
+        assert_eq!(None, shows[2].thetvdb);
+        assert_eq!(None, shows[2].episodes[0].watched);
+    }
+
+    #[test]
+    fn parse_kodi_played_episodes() {
+        let records = parse_csv_records(
+            "show_id,title,season,number,last_played,tvdb,imdb
+1,The Orville,1,1,\"2026-03-02 20:00:00\",328487,tt5691552
+1,The Orville,1,2,,328487,tt5691552
+2,Severance,1,1,\"2026-03-03 21:00:00\",,
+3,,1,1,,,
+",
+        );
+
+        let shows = parse_kodi_episodes(&records);
+        assert_eq!(2, shows.len());
+
+        assert_eq!("The Orville", shows[0].title);
+        assert_eq!(Some(328487), shows[0].thetvdb);
+        assert_eq!(Some("tt5691552".to_string()), shows[0].imdb);
+        let episodes: Vec<(usize, usize)> = shows[0]
+            .episodes
+            .iter()
+            .map(|episode| (episode.season, episode.number))
+            .collect();
+        assert_eq!(vec![(1, 1), (1, 2)], episodes);
+        assert!(shows[0].episodes[0].watched.is_some());
+        assert_eq!(None, shows[0].episodes[1].watched);
+
+        assert_eq!(None, shows[1].thetvdb);
+        assert_eq!(None, shows[1].imdb);
+        assert_eq!(1, shows[1].episodes.len());
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use futures::{Future, Stream};
+use hyper::client::HttpConnector;
+use hyper::header::HeaderValue;
+use hyper::{Body, Client, Request, StatusCode, Uri};
+use hyper_tls::HttpsConnector;
+use serde::de::DeserializeOwned;
+use tokio_core::reactor::Core;
+
+use config::JellyfinConfig;
+use errors::*;
+use import::{ImportedEpisode, ImportedShow};
+
+/// Header with the API key, understood by both Jellyfin and Emby.
+const TOKEN_HEADER: &str = "X-Emby-Token";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct User {
+    name: String,
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Items<T> {
+    items: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Series {
+    id: String,
+    name: String,
+    #[serde(default)]
+    provider_ids: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ItemUserData {
+    #[serde(default)]
+    played: bool,
+    last_played_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EpisodeItem {
+    series_id: Option<String>,
+    parent_index_number: Option<usize>,
+    index_number: Option<usize>,
+    user_data: Option<ItemUserData>,
+}
+
+/// Client for the played status of TV episodes on a Jellyfin (or Emby) server.
+pub struct Jellyfin {
+    core: Core,
+    client: Client<HttpsConnector<HttpConnector>>,
+    url: String,
+    api_key: String,
+    user: Option<String>,
+}
+
+/// Looks up a provider id (e.g. "Tvdb"), ignoring case.
+fn provider_id<'a>(series: &'a Series, provider: &str) -> Option<&'a str> {
+    series
+        .provider_ids
+        .iter()
+        .find(|&(name, _)| name.eq_ignore_ascii_case(provider))
+        .map(|(_, id)| id.trim())
+        .filter(|id| !id.is_empty())
+}
+
+/// Groups played episodes by series, skipping series without played episodes.
+fn played_shows(series: Vec<Series>, episodes: &[EpisodeItem]) -> Vec<ImportedShow> {
+    series
+        .into_iter()
+        .filter_map(|series| {
+            let episodes: Vec<ImportedEpisode> = episodes
+                .iter()
+                .filter(|episode| episode.series_id.as_ref() == Some(&series.id))
+                .filter_map(|episode| match episode.user_data {
+                    Some(ref user_data) if user_data.played => Some(ImportedEpisode {
+                        season: episode.parent_index_number?,
+                        number: episode.index_number?,
+                        watched: user_data.last_played_date,
+                    }),
+                    _ => None,
+                })
+                .collect();
+
+            if episodes.is_empty() {
+                return None;
+            }
+
+            Some(ImportedShow {
+                thetvdb: provider_id(&series, "Tvdb").and_then(|id| id.parse().ok()),
+                imdb: provider_id(&series, "Imdb").map(|id| id.to_string()),
+                title: series.name,
+                episodes,
+            })
+        })
+        .collect()
+}
+
+impl Jellyfin {
+    pub fn new(config: &JellyfinConfig) -> Result<Self> {
+        Ok(Self {
+            core: Core::new()?,
+            client: Client::builder().build(HttpsConnector::new(4)?),
+            url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            user: config.user.clone(),
+        })
+    }
+
+    fn get<T: DeserializeOwned>(&mut self, path: &str) -> Result<T> {
+        let uri = Uri::from_str(&format!("{}{}", self.url, path))
+            .chain_err(|| format!("Invalid Jellyfin URL [{}]", self.url))?;
+        let request = Request::get(uri.clone())
+            .header(
+                TOKEN_HEADER,
+                HeaderValue::from_str(&self.api_key).chain_err(|| "Invalid Jellyfin API key")?,
+            )
+            .body(Body::empty())
+            .chain_err(|| "Unable to create Jellyfin request")?;
+
+        let response = self
+            .client
+            .request(request)
+            .from_err::<Error>()
+            .and_then(move |res| {
+                let status = res.status();
+                res.into_body()
+                    .concat2()
+                    .from_err()
+                    .and_then(move |body| match status {
+                        StatusCode::OK => ::serde_json::from_slice(&body).map_err(Error::from),
+                        status => Err(ErrorKind::HttpError(status, uri).into()),
+                    })
+            });
+
+        self.core
+            .run(response)
+            .chain_err(|| "Jellyfin request failed")
+    }
+
+    /// Id of the configured user, or of the only user if none is configured.
+    fn user_id(&mut self) -> Result<String> {
+        let users: Vec<User> = self.get("/Users")?;
+
+        let user = match self.user {
+            Some(ref name) => users
+                .into_iter()
+                .find(|user| user.name.to_lowercase() == name.to_lowercase())
+                .ok_or_else(|| Error::from(format!("Jellyfin user [{}] not found", name)))?,
+            None if users.len() == 1 => users.into_iter().next().unwrap(),
+            None => bail!(
+                "Please choose a Jellyfin user: {}",
+                users
+                    .iter()
+                    .map(|user| user.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+
+        Ok(user.id)
+    }
+
+    /// Series with their played episodes and TheTVDB or IMDb ids.
+    pub fn played_shows(&mut self) -> Result<Vec<ImportedShow>> {
+        let user_id = self.user_id()?;
+
+        let series: Items<Series> = self.get(&format!(
+            "/Users/{}/Items?IncludeItemTypes=Series&Recursive=true&Fields=ProviderIds",
+            user_id
+        ))?;
+        let episodes: Items<EpisodeItem> = self.get(&format!(
+            "/Users/{}/Items?IncludeItemTypes=Episode&Recursive=true&IsPlayed=true",
+            user_id
+        ))?;
+
+        Ok(played_shows(series.items, &episodes.items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::SocketAddr;
+    use std::thread;
+
+    use hyper::service::service_fn_ok;
+    use hyper::{Response, Server};
+
+    /// Answers like a Jellyfin server with a single user and two series.
+    fn start_jellyfin_stand_in() -> SocketAddr {
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(|| {
+            service_fn_ok(|request: Request<Body>| {
+                let authorized = request.headers().get(TOKEN_HEADER)
+                    == Some(&HeaderValue::from_static("secret"));
+                let body = match (request.uri().path(), request.uri().query()) {
+                    _ if !authorized => None,
+                    ("/jellyfin/Users", _) => Some(r#"[{"Name": "Me", "Id": "u1"}]"#),
+                    ("/jellyfin/Users/u1/Items", Some(query))
+                        if query.contains("IncludeItemTypes=Series") =>
+                    {
+                        Some(
+                            r#"{"Items": [
+                                {"Name": "The Orville", "Id": "s1",
+                                 "ProviderIds": {"Tvdb": "328487", "Imdb": "tt5691552"}},
+                                {"Name": "Unwatched", "Id": "s2", "ProviderIds": {}}],
+                                "TotalRecordCount": 2}"#,
+                        )
+                    }
+                    ("/jellyfin/Users/u1/Items", Some(_)) => Some(
+                        r#"{"Items": [
+                            {"SeriesId": "s1", "ParentIndexNumber": 1, "IndexNumber": 2,
+                             "UserData": {"Played": true,
+                                          "LastPlayedDate": "2026-03-02T20:00:00.0000000Z"}},
+                            {"SeriesId": "s1", "ParentIndexNumber": 1, "IndexNumber": 1,
+                             "UserData": {"Played": true}},
+                            {"SeriesId": "s1", "IndexNumber": 3,
+                             "UserData": {"Played": true}}],
+                            "TotalRecordCount": 3}"#,
+                    ),
+                    _ => None,
+                };
+
+                match body {
+                    Some(body) => Response::new(Body::from(body)),
+                    None => Response::builder()
+                        .status(StatusCode::UNAUTHORIZED)
+                        .body(Body::empty())
+                        .unwrap(),
+                }
+            })
+        });
+
+        let addr = server.local_addr();
+        thread::spawn(move || hyper::rt::run(server.map_err(|e| panic!("{}", e))));
+
+        addr
+    }
+
+    #[test]
+    fn read_played_episodes() {
+        let addr = start_jellyfin_stand_in();
+        let mut config = JellyfinConfig {
+            url: format!("http://{}/jellyfin/", addr),
+            api_key: "secret".to_string(),
+            user: None,
+        };
+
+        let shows = Jellyfin::new(&config).unwrap().played_shows().unwrap();
+        assert_eq!(1, shows.len());
+        assert_eq!("The Orville", shows[0].title);
+        assert_eq!(Some(328487), shows[0].thetvdb);
+        assert_eq!(Some("tt5691552".to_string()), shows[0].imdb);
+        let episodes: Vec<(usize, usize)> = shows[0]
+            .episodes
+            .iter()
+            .map(|episode| (episode.season, episode.number))
+            .collect();
+        assert_eq!(vec![(1, 2), (1, 1)], episodes);
+        assert_eq!(
+            "2026-03-02T20:00:00Z".parse::<DateTime<Utc>>().ok(),
+            shows[0].episodes[0].watched
+        );
+
+        config.user = Some("someone else".to_string());
+        assert!(Jellyfin::new(&config).unwrap().played_shows().is_err());
+
+        config.user = None;
+        config.api_key = "wrong".to_string();
+        assert!(Jellyfin::new(&config).unwrap().played_shows().is_err());
+    }
+}
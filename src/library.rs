@@ -0,0 +1,423 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDate};
+
+use errors::*;
+use tvmaze_api::{Episode, Show};
+
+/// File in the data directory with the video files found by the last scan.
+const LIBRARY_FILE: &str = "library.json";
+
+/// File extensions of video files, other files are ignored when scanning.
+const VIDEO_EXTENSIONS: [&str; 9] = [
+    "avi", "m2ts", "m4v", "mkv", "mov", "mp4", "mpg", "ts", "wmv",
+];
+
+/// Episode as referenced in a file name.
+#[derive(Debug, PartialEq)]
+pub enum EpisodeRef {
+    /// E.g. `S02E05` or `2x05`
+    Numbered(usize, usize),
+    /// E.g. `2026.03.02` or `2026-03-02`
+    Aired(NaiveDate),
+}
+
+/// Video file with the show name (normalized) and episode parsed from its path.
+#[derive(Debug, PartialEq)]
+pub struct MediaFile {
+    pub path: PathBuf,
+    pub show: String,
+    pub episode: EpisodeRef,
+}
+
+/// Lower case words of a name, separated by a single space, e.g. "star trek discovery" for
+/// both "Star Trek: Discovery" and "Star.Trek.Discovery".
+pub fn normalize_name(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Removes a trailing year from a normalized name, e.g. "doctor who" for "doctor who 2005".
+fn without_year(name: &str) -> &str {
+    match name.rfind(' ') {
+        Some(index)
+            if name.len() - index == 5 && name[index + 1..].chars().all(|c| c.is_ascii_digit()) =>
+        {
+            &name[..index]
+        }
+        _ => name,
+    }
+}
+
+fn parse_number(digits: &str) -> Option<usize> {
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    digits.parse().ok()
+}
+
+/// Parses `S02E05` (also `S02E05E06`, of which the first episode counts) or `2x05`.
+fn parse_episode_number(word: &str) -> Option<EpisodeRef> {
+    let word = word.to_lowercase();
+
+    if word.starts_with('s') {
+        let index = word.find('e')?;
+        let season = parse_number(&word[1..index])?;
+        let number = word[index + 1..].split('e').next().and_then(parse_number)?;
+        return Some(EpisodeRef::Numbered(season, number));
+    }
+
+    let index = word.find('x')?;
+    if index > 2 {
+        return None;
+    }
+    let season = parse_number(&word[..index])?;
+    let number = parse_number(&word[index + 1..])?;
+
+    Some(EpisodeRef::Numbered(season, number))
+}
+
+/// Parses a date from three consecutive words, e.g. `2026 03 02`.
+fn parse_date(words: &[&str]) -> Option<EpisodeRef> {
+    match *words {
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+            NaiveDate::from_ymd_opt(
+                parse_number(year)? as i32,
+                parse_number(month)? as u32,
+                parse_number(day)? as u32,
+            )
+            .map(EpisodeRef::Aired)
+        }
+        _ => None,
+    }
+}
+
+/// Parses the show name and episode from a file name (without extension). The show name is
+/// empty if the file name only consists of the episode, e.g. `S02E05 - Title`.
+pub fn parse_file_name(name: &str) -> Option<(String, EpisodeRef)> {
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    for i in 0..words.len() {
+        let episode = match parse_episode_number(words[i]) {
+            Some(episode) => episode,
+            None => match words.get(i..i + 3).and_then(parse_date) {
+                Some(episode) => episode,
+                None => continue,
+            },
+        };
+
+        return Some((normalize_name(&words[..i].join(" ")), episode));
+    }
+
+    None
+}
+
+/// Parses a video file. If its name doesn't contain the show, the show is taken from the
+/// closest directory that isn't a season directory, e.g. `The Orville/Season 1/S01E05.mkv`.
+pub fn parse_path(path: &Path) -> Option<MediaFile> {
+    let (show, episode) = parse_file_name(&path.file_stem()?.to_string_lossy())?;
+
+    let show = if show.is_empty() {
+        path.ancestors()
+            .skip(1)
+            .filter_map(|directory| directory.file_name())
+            .map(|directory| normalize_name(&directory.to_string_lossy()))
+            .find(|directory| {
+                !directory.is_empty() && !directory.starts_with("season") && directory != "specials"
+            })?
+    } else {
+        show
+    };
+
+    Some(MediaFile {
+        path: path.to_path_buf(),
+        show,
+        episode,
+    })
+}
+
+fn is_video_file(path: &Path) -> bool {
+    match path.extension() {
+        Some(extension) => {
+            VIDEO_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())
+        }
+        None => false,
+    }
+}
+
+/// Recursively collects the video files in the given directory.
+pub fn scan(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    let entries = fs::read_dir(directory)
+        .chain_err(|| format!("Unable to read directory {:?}", directory))?;
+    for entry in entries {
+        let path = entry
+            .chain_err(|| format!("Unable to read directory {:?}", directory))?
+            .path();
+
+        if path.is_dir() {
+            files.extend(scan(&path)?);
+        } else if is_video_file(&path) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Whether the episode aired on the given date.
+///
+/// Dates in file names are local air dates, but only the UTC air time is known. Shifting it
+/// by six hours approximates the local date: shows airing in the evening in the Americas are
+/// after midnight UTC, while elsewhere local time is ahead of UTC.
+fn aired_on(episode: &Episode, date: NaiveDate) -> bool {
+    match episode.airstamp {
+        Some(airstamp) => (airstamp - Duration::hours(6)).naive_utc().date() == date,
+        None => false,
+    }
+}
+
+/// Matches video files to unwatched episodes of the subscribed shows.
+///
+/// Returns the paths by episode id, as well as the files that didn't match any episode.
+pub fn match_files<'a>(
+    files: &'a [MediaFile],
+    shows: &[Show],
+    episodes: &[Episode],
+) -> (BTreeMap<usize, PathBuf>, Vec<&'a MediaFile>) {
+    let mut matched = BTreeMap::new();
+    let mut unmatched = Vec::new();
+
+    for file in files {
+        let show = shows.iter().find(|show| {
+            let name = normalize_name(&show.name);
+            name == file.show || without_year(&name) == without_year(&file.show)
+        });
+
+        let episode = show.and_then(|show| {
+            episodes.iter().find(|episode| {
+                episode.show_id == show.id
+                    && !episode.watched
+                    && match file.episode {
+                        EpisodeRef::Numbered(season, number) => {
+                            (episode.season, episode.number) == (season, number)
+                        }
+                        EpisodeRef::Aired(date) => aired_on(episode, date),
+                    }
+            })
+        });
+
+        match episode {
+            Some(episode) => {
+                matched.insert(episode.episode_id, file.path.clone());
+            }
+            None => unmatched.push(file),
+        }
+    }
+
+    (matched, unmatched)
+}
+
+/// Video files of unwatched episodes found by the last scan, by episode id.
+///
+/// The paths are only valid on this machine, so they are kept in a file of their own next to
+/// the user data, which is synchronized between machines.
+pub struct Library {
+    path: PathBuf,
+    files: BTreeMap<usize, PathBuf>,
+}
+
+impl Library {
+    /// Loads the library of the given data directory. Files of episodes that have been watched
+    /// since the last scan are dropped.
+    pub fn load(data_dir: &Path, episodes: &[Episode]) -> Result<Self> {
+        let path = data_dir.join(LIBRARY_FILE);
+
+        let files = match fs::read_to_string(&path) {
+            Ok(json) => ::serde_json::from_str(&json)
+                .chain_err(|| format!("Unable to parse media library {:?}", path))?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(Error::from(e)).chain_err(|| format!("Unable to read {:?}", path))
+            }
+        };
+
+        let mut library = Library { path, files };
+        library.prune(episodes);
+        Ok(library)
+    }
+
+    pub fn files(&self) -> &BTreeMap<usize, PathBuf> {
+        &self.files
+    }
+
+    /// Replaces the files, e.g. after scanning the media library.
+    pub fn set_files(&mut self, files: BTreeMap<usize, PathBuf>) {
+        self.files = files;
+    }
+
+    /// Stores the files of the episodes that are still unwatched.
+    pub fn store(&mut self, episodes: &[Episode]) -> Result<()> {
+        self.prune(episodes);
+
+        let json = ::serde_json::to_string_pretty(&self.files)
+            .chain_err(|| "Unable to serialize media library.")?;
+        if let Some(data_dir) = self.path.parent() {
+            fs::create_dir_all(data_dir)
+                .chain_err(|| format!("Unable to create data directory {:?}", data_dir))?;
+        }
+        fs::write(&self.path, json).chain_err(|| format!("Unable to write {:?}", self.path))
+    }
+
+    /// Drops the files of episodes that aren't unwatched anymore.
+    fn prune(&mut self, episodes: &[Episode]) {
+        self.files.retain(|episode_id, _| {
+            episodes
+                .iter()
+                .any(|episode| episode.episode_id == *episode_id && !episode.watched)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use chrono::{DateTime, Utc};
+
+    use user_data::tests::show;
+
+    #[test]
+    fn parse_file_names() {
+        assert_eq!(
+            Some(("the orville".to_string(), EpisodeRef::Numbered(2, 5))),
+            parse_file_name("The.Orville.S02E05.1080p.WEB.x264")
+        );
+        assert_eq!(
+            Some(("the orville".to_string(), EpisodeRef::Numbered(1, 3))),
+            parse_file_name("the orville s01e03e04")
+        );
+        assert_eq!(
+            Some((
+                "star trek discovery".to_string(),
+                EpisodeRef::Numbered(2, 5)
+            )),
+            parse_file_name("Star Trek - Discovery - 2x05 - Saints of Imperfection")
+        );
+        assert_eq!(
+            Some((
+                "the daily show".to_string(),
+                EpisodeRef::Aired(NaiveDate::from_ymd(2026, 3, 2))
+            )),
+            parse_file_name("The.Daily.Show.2026.03.02.720p")
+        );
+        assert_eq!(None, parse_file_name("The.Orville.Trailer.1080p"));
+        assert_eq!(None, parse_file_name("Show.2026.13.02"));
+
+        let file = parse_path(Path::new(
+            "/media/tv/The Orville/Season 1/S01E05 - Pria.mkv",
+        ));
+        assert_eq!("the orville", file.unwrap().show);
+    }
+
+    #[test]
+    fn match_files_to_episodes() {
+        let shows = vec![show(20263, "The Orville"), show(210, "Doctor Who (2005)")];
+        let episode = |episode_id, show_id, number, airstamp: &str, watched| Episode {
+            episode_id,
+            show_id,
+            name: format!("Episode {}", number),
+            season: 1,
+            number,
+            airstamp: airstamp.parse::<DateTime<Utc>>().ok(),
+            runtime: None,
+            watched,
+        };
+        let episodes = vec![
+            episode(1, 20263, 4, "2017-10-06T01:00:00Z", false),
+            episode(2, 20263, 5, "2017-10-13T01:00:00Z", true),
+            episode(3, 210, 1, "2005-03-26T18:00:00Z", false),
+        ];
+
+        let files: Vec<MediaFile> = [
+            "/tv/The.Orville.S01E04.mkv",
+            "/tv/The.Orville.S01E05.mkv",
+            "/tv/The.Orville.2017.10.12.mkv",
+            "/tv/Doctor.Who.2005.03.26.mkv",
+            "/tv/Unknown.Show.S01E01.mkv",
+        ]
+        .iter()
+        .filter_map(|path| parse_path(Path::new(path)))
+        .collect();
+
+        let (matched, unmatched) = match_files(&files, &shows, &episodes);
+        assert_eq!(
+            vec![
+                (1, PathBuf::from("/tv/The.Orville.S01E04.mkv")),
+                (3, PathBuf::from("/tv/Doctor.Who.2005.03.26.mkv")),
+            ],
+            matched.into_iter().collect::<Vec<_>>()
+        );
+        // Already watched (matched by number and by air date in the evening in the US)
+        assert_eq!(3, unmatched.len());
+        assert_eq!(
+            Path::new("/tv/The.Orville.2017.10.12.mkv"),
+            unmatched[1].path
+        );
+    }
+
+    #[test]
+    fn drop_files_of_watched_episodes() {
+        let data_dir = env::temp_dir().join("bingers_library_test");
+        let _ = fs::remove_dir_all(&data_dir);
+        fs::create_dir_all(&data_dir).unwrap();
+
+        let episode = |episode_id, watched| Episode {
+            episode_id,
+            show_id: 20263,
+            name: format!("Episode {}", episode_id),
+            season: 1,
+            number: episode_id,
+            airstamp: None,
+            runtime: None,
+            watched,
+        };
+        let mut episodes = vec![episode(1, false), episode(2, false), episode(3, false)];
+
+        let mut library = Library::load(&data_dir, &episodes).unwrap();
+        assert!(library.files().is_empty());
+
+        library.set_files(
+            (1..5)
+                .map(|id| {
+                    (
+                        id,
+                        PathBuf::from(format!("/tv/The.Orville.S01E0{}.mkv", id)),
+                    )
+                })
+                .collect(),
+        );
+        episodes[1].watched = true;
+        library.store(&episodes).unwrap();
+        assert_eq!(vec![&1, &3], library.files().keys().collect::<Vec<_>>());
+
+        // Watched or removed since the last scan
+        episodes.truncate(2);
+        let library = Library::load(&data_dir, &episodes).unwrap();
+        assert_eq!(vec![&1], library.files().keys().collect::<Vec<_>>());
+    }
+}
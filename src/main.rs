@@ -5,7 +5,13 @@ extern crate futures;
 extern crate hyper;
 extern crate hyper_tls;
 extern crate percent_encoding;
+extern crate rand;
+extern crate rustyline;
+extern crate serde;
 extern crate serde_json;
+extern crate shell_words;
+extern crate signal_hook;
+extern crate termion;
 extern crate tokio_core;
 extern crate tokio_retry;
 extern crate tokio_timer;
@@ -17,20 +23,47 @@ extern crate serde_derive;
 extern crate error_chain;
 
 mod app;
+mod config;
+mod daemon;
+mod digest;
 mod errors;
+mod export;
+mod import;
+mod jellyfin;
+mod library;
+mod plan;
+mod server;
+mod shell;
+mod smtp;
+mod stats;
+mod sync;
+mod tui;
 mod tvmaze_api;
 mod user_data;
+mod webdav;
+
+use std::path::Path;
 
 use clap::{Arg, SubCommand};
 
 use error_chain::ChainedError;
 use errors::*;
 
-use app::App;
+use chrono::NaiveDate;
+
+use app::{App, ExportFormat, NextUpOrder};
+use config::{Config, JellyfinConfig};
+use daemon::DaemonOptions;
+use jellyfin::Jellyfin;
+use tvmaze_api::ViewingState;
 
 fn run(matches: &clap::ArgMatches) -> Result<()> {
     let mut app = App::new()?;
 
+    run_subcommand(&mut app, matches)
+}
+
+fn run_subcommand(app: &mut App, matches: &clap::ArgMatches) -> Result<()> {
     // Dispatch to subcommands
     match matches.subcommand() {
         ("add", Some(m)) => {
@@ -38,10 +71,12 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
             app.add_show(show)?;
         }
         ("list", Some(m)) => {
+            let tag = m.value_of("tag");
+
             if m.is_present("shows") {
-                app.list_shows()?;
+                app.list_shows(tag, m.is_present("progress"), m.is_present("by_completion"))?;
             } else {
-                app.list_episodes()?;
+                app.list_episodes(tag)?;
             }
         }
         ("remove", Some(m)) => {
@@ -61,11 +96,229 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
                 None => None,
             };
 
-            app.mark_as_watched(show, season, episode)?;
+            let rating = match m.value_of("rate") {
+                Some(rating) => Some(rating.parse::<u8>()?),
+                None => None,
+            };
+
+            let note = m.value_of("note").map(|note| note.to_string());
+
+            app.mark_as_watched(show, season, episode, rating, note)?;
+        }
+        ("unwatched", Some(m)) => {
+            let show = m.value_of("tv_show").unwrap();
+
+            let season = match m.value_of("season") {
+                Some(season) => Some(season.parse::<usize>()?),
+                None => None,
+            };
+
+            let episode = match m.value_of("episode") {
+                Some(episode) => Some(episode.parse::<usize>()?),
+                None => None,
+            };
+
+            app.mark_as_unwatched(show, season, episode)?;
+        }
+        ("tag", Some(m)) => {
+            let show = m.value_of("tv_show").unwrap();
+            let tags: Vec<&str> = m.values_of("tags").unwrap().collect();
+            app.tag_show(show, &tags, m.is_present("remove"))?;
+        }
+        ("tags", Some(_)) => {
+            app.list_tags()?;
+        }
+        ("note", Some(m)) => {
+            let show = m.value_of("tv_show").unwrap();
+
+            let delete = match m.value_of("delete") {
+                Some(delete) => Some(delete.parse::<usize>()?),
+                None => None,
+            };
+
+            app.edit_notes(show, m.value_of("note"), delete)?;
+        }
+        ("field", Some(m)) => {
+            let show = m.value_of("tv_show").unwrap();
+            let key = m.value_of("key").unwrap();
+            app.edit_field(show, key, m.value_of("value"))?;
+        }
+        ("info", Some(m)) => {
+            let show = m.value_of("tv_show").unwrap();
+            app.show_info(show)?;
+        }
+        ("search", Some(m)) => {
+            let query = m.value_of("query").unwrap();
+            app.search(query)?;
+        }
+        ("priority", Some(m)) => {
+            let show = m.value_of("tv_show").unwrap();
+            let priority = m.value_of("priority").unwrap().parse::<i32>()?;
+            app.set_priority(show, priority)?;
+        }
+        ("plan", Some(m)) => {
+            let until = match m.value_of("until") {
+                Some(until) => Some(
+                    NaiveDate::parse_from_str(until, "%Y-%m-%d")
+                        .chain_err(|| format!("Invalid date [{}], expected YYYY-MM-DD", until))?,
+                ),
+                None => None,
+            };
+
+            let per_day = match m.value_of("per_day") {
+                Some(per_day) => Some(daemon::parse_duration(per_day)?.as_secs() as usize / 60),
+                None => None,
+            };
+
+            app.plan(until, per_day, m.value_of("tag"))?;
+        }
+        ("import", Some(m)) => {
+            let path = Path::new(m.value_of("path").unwrap_or_default());
+            let source = m.value_of("source").unwrap();
+            let dry_run = m.is_present("dry_run");
+
+            if source == "bingers" {
+                app.import_export(path, m.is_present("replace"), dry_run)?;
+            } else if m.is_present("replace") {
+                bail!("--replace is only supported when importing a bingers export");
+            } else if source == "kodi" {
+                app.import_kodi(import::read_kodi_database(path)?, dry_run)?;
+            } else {
+                let shows = match source {
+                    "trakt" => import::read_trakt_export(path)?,
+                    "tvtime" => import::read_tvtime_export(path)?,
+                    "jellyfin" => {
+                        let config = match (m.value_of("url"), m.value_of("api_key")) {
+                            (Some(url), Some(api_key)) => JellyfinConfig {
+                                url: url.to_string(),
+                                api_key: api_key.to_string(),
+                                user: m.value_of("user").map(|user| user.to_string()),
+                            },
+                            _ => match Config::load()?.jellyfin {
+                                Some(mut config) => {
+                                    if let Some(user) = m.value_of("user") {
+                                        config.user = Some(user.to_string());
+                                    }
+                                    config
+                                }
+                                None => bail!(
+                                    "No Jellyfin server configured, please provide --url and --api-key"
+                                ),
+                            },
+                        };
+                        Jellyfin::new(&config)?.played_shows()?
+                    }
+                    source => bail!("Invalid import source [{}]", source),
+                };
+                app.import(shows, dry_run)?;
+            }
+        }
+        ("library", Some(m)) => match m.subcommand() {
+            ("scan", Some(m)) => app.library_scan(Path::new(m.value_of("directory").unwrap()))?,
+            ("missing", Some(m)) => app.library_missing(m.value_of("tag"))?,
+            _ => println!("{}", m.usage()),
+        },
+        ("merge", Some(m)) => {
+            app.merge(Path::new(m.value_of("file").unwrap()))?;
+        }
+        ("sync", Some(m)) => match m.value_of("git") {
+            Some(repository) => sync::sync_git(app, Path::new(repository))?,
+            None => match Config::load()?.webdav {
+                Some(webdav) => sync::sync_webdav(app, &webdav)?,
+                None => bail!("No WebDAV folder configured in {:?}", Config::path()?),
+            },
+        },
+        ("export", Some(m)) => {
+            let format = match m.value_of("format").unwrap() {
+                "bingers" => ExportFormat::Bingers,
+                "trakt" => ExportFormat::Trakt,
+                format => bail!("Invalid export format [{}]", format),
+            };
+            app.export(&format)?;
+        }
+        ("next", Some(m)) => {
+            let order = match m.value_of("sort").unwrap() {
+                "activity" => NextUpOrder::LastActivity,
+                "air-date" => NextUpOrder::AirDate,
+                "priority" => NextUpOrder::Priority,
+                sort => bail!("Invalid sort order [{}]", sort),
+            };
+            app.next_up(m.value_of("tag"), &order)?;
+        }
+        ("pick", Some(m)) => {
+            let minutes =
+                daemon::parse_duration(m.value_of("minutes").unwrap())?.as_secs() as usize / 60;
+            let tag = m.value_of("tag");
+            app.pick(minutes, tag, m.is_present("random"))?;
+        }
+        ("stats", Some(m)) => {
+            let year = match m.value_of("year") {
+                Some(year) => Some(year.parse::<i32>()?),
+                None => None,
+            };
+            app.stats(year, m.is_present("json"))?;
+        }
+        ("state", Some(m)) => {
+            let show = m.value_of("tv_show").unwrap();
+            let reason = m.value_of("reason").map(|reason| reason.to_string());
+            let state = m.value_of("state").unwrap();
+
+            if reason.is_some() && state != "dropped" {
+                bail!("--reason is only supported when dropping a show");
+            }
+
+            let viewing_state = match state {
+                "watching" => ViewingState::Watching,
+                "paused" => ViewingState::Paused,
+                "dropped" => ViewingState::Dropped(reason),
+                "completed" => ViewingState::Completed,
+                "plan-to-watch" => ViewingState::PlanToWatch,
+                state => bail!("Invalid state [{}]", state),
+            };
+
+            app.set_viewing_state(show, viewing_state)?;
+        }
+        ("ratings", Some(m)) => {
+            let show = m.value_of("tv_show").unwrap();
+            app.ratings(show)?;
         }
         ("update", Some(m)) => {
             let force = m.is_present("force");
-            app.update(force)?;
+            let tag = m.value_of("tag");
+            app.update(force, tag)?;
+        }
+        ("digest", Some(m)) => {
+            let smtp_server = m.value_of("smtp").unwrap();
+            let to = m.value_of("to").unwrap();
+            let from = m.value_of("from").unwrap();
+            app.digest(smtp_server, from, to, m.value_of("tag"))?;
+        }
+        ("daemon", Some(m)) => {
+            if m.is_present("refresh") {
+                daemon::trigger_refresh()?;
+                return Ok(());
+            }
+
+            let options = DaemonOptions {
+                interval: daemon::parse_duration(m.value_of("interval").unwrap())?,
+                jitter: daemon::parse_duration(m.value_of("jitter").unwrap())?,
+                hook: m.value_of("hook").map(|hook| hook.to_string()),
+            };
+
+            daemon::run(app, &options)?;
+        }
+        ("serve", Some(m)) => {
+            let listen = m.value_of("listen").unwrap();
+            let addr = listen
+                .parse()
+                .chain_err(|| format!("Invalid address [{}]", listen))?;
+            server::run(app, &addr)?;
+        }
+        ("tui", Some(_)) => {
+            tui::run(app)?;
+        }
+        ("shell", Some(_)) => {
+            shell::run(app)?;
         }
         _ => {
             println!("{}", matches.usage());
@@ -77,9 +330,41 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
     Ok(())
 }
 
-fn main() {
-    // Parse arguments
-    let matches = clap::App::new("bingers")
+/// Names of the subcommands of `build_cli()`, e.g. for completion in the shell.
+const SUBCOMMANDS: [&str; 29] = [
+    "add",
+    "list",
+    "remove",
+    "watched",
+    "tag",
+    "tags",
+    "note",
+    "field",
+    "info",
+    "search",
+    "priority",
+    "plan",
+    "import",
+    "library",
+    "merge",
+    "sync",
+    "export",
+    "next",
+    "pick",
+    "stats",
+    "state",
+    "ratings",
+    "unwatched",
+    "update",
+    "digest",
+    "daemon",
+    "serve",
+    "tui",
+    "shell",
+];
+
+fn build_cli() -> clap::App<'static, 'static> {
+    clap::App::new("bingers")
         .version("0.1")
         .author("Dominik Fankhauser")
         .about("Manage your TV shows from the command line")
@@ -109,6 +394,27 @@ When no flag is given, episodes will be listed.",
                         .long("episodes")
                         .conflicts_with("shows")
                         .help("List episodes (default)"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .short("t")
+                        .long("tag")
+                        .takes_value(true)
+                        .help("Only list shows with this tag"),
+                )
+                .arg(
+                    Arg::with_name("progress")
+                        .short("p")
+                        .long("progress")
+                        .requires("shows")
+                        .help("Show progress of each season"),
+                )
+                .arg(
+                    Arg::with_name("by_completion")
+                        .short("c")
+                        .long("by-completion")
+                        .requires("shows")
+                        .help("Sort shows by completion"),
                 ),
         )
         .subcommand(
@@ -142,6 +448,448 @@ Use the --season and --episode arguments to override.",
                              If used without --episode, will mark whole season as watched.",
                         ),
                 )
+                .arg(
+                    Arg::with_name("episode")
+                        .short("e")
+                        .long("episode")
+                        .takes_value(true)
+                        .requires("season")
+                        .help("Specify episode"),
+                )
+                .arg(
+                    Arg::with_name("rate")
+                        .short("r")
+                        .long("rate")
+                        .takes_value(true)
+                        .value_name("RATING")
+                        .help("Rate episode (1 - 10)"),
+                )
+                .arg(
+                    Arg::with_name("note")
+                        .short("n")
+                        .long("note")
+                        .takes_value(true)
+                        .help("Attach a note to the episode"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("tag")
+                .about(
+                    "Tag show\n
+Tags group shows into collections, which can be used to filter list and update.",
+                )
+                .arg(
+                    Arg::with_name("tv_show")
+                        .required(true)
+                        .index(1)
+                        .value_name("SHOW"),
+                )
+                .arg(
+                    Arg::with_name("tags")
+                        .required(true)
+                        .multiple(true)
+                        .index(2)
+                        .value_name("TAG"),
+                )
+                .arg(
+                    Arg::with_name("remove")
+                        .short("r")
+                        .long("remove")
+                        .help("Remove tags instead of adding them"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("tags").about("List tags and the shows that have them"))
+        .subcommand(
+            SubCommand::with_name("note")
+                .about("Attach a note to show")
+                .arg(
+                    Arg::with_name("tv_show")
+                        .required(true)
+                        .index(1)
+                        .value_name("SHOW"),
+                )
+                .arg(
+                    Arg::with_name("note")
+                        .required_unless("delete")
+                        .conflicts_with("delete")
+                        .index(2)
+                        .value_name("NOTE"),
+                )
+                .arg(
+                    Arg::with_name("delete")
+                        .short("d")
+                        .long("delete")
+                        .takes_value(true)
+                        .value_name("NUMBER")
+                        .help("Delete note (numbered as in info)"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("field")
+                .about(
+                    "Set custom field of show\n
+If no value is given, the field is removed.",
+                )
+                .arg(
+                    Arg::with_name("tv_show")
+                        .required(true)
+                        .index(1)
+                        .value_name("SHOW"),
+                )
+                .arg(
+                    Arg::with_name("key")
+                        .required(true)
+                        .index(2)
+                        .value_name("KEY"),
+                )
+                .arg(Arg::with_name("value").index(3).value_name("VALUE")),
+        )
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Show details of show, progress per season, notes and custom fields")
+                .arg(
+                    Arg::with_name("tv_show")
+                        .required(true)
+                        .index(1)
+                        .value_name("SHOW"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about("Search names, tags, notes and custom fields of subscribed shows")
+                .arg(
+                    Arg::with_name("query")
+                        .required(true)
+                        .index(1)
+                        .value_name("QUERY"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("priority")
+                .about("Set priority of show (higher priorities are planned first, default: 0)")
+                .arg(
+                    Arg::with_name("tv_show")
+                        .required(true)
+                        .index(1)
+                        .value_name("SHOW"),
+                )
+                .arg(
+                    Arg::with_name("priority")
+                        .required(true)
+                        .index(2)
+                        .allow_hyphen_values(true)
+                        .value_name("PRIORITY"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("plan")
+                .about(
+                    "Plan when to watch unwatched episodes\n
+Episodes of shows with higher priority are planned first, otherwise in air order.
+Without --per-day, calculates how much to watch per day to finish by the deadline.",
+                )
+                .arg(
+                    Arg::with_name("until")
+                        .short("u")
+                        .long("until")
+                        .takes_value(true)
+                        .value_name("DATE")
+                        .required_unless("per_day")
+                        .help("Deadline (YYYY-MM-DD)"),
+                )
+                .arg(
+                    Arg::with_name("per_day")
+                        .short("p")
+                        .long("per-day")
+                        .takes_value(true)
+                        .value_name("DURATION")
+                        .help("Time to watch per day, e.g. 90m or 2h"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .short("t")
+                        .long("tag")
+                        .takes_value(true)
+                        .help("Only plan episodes of shows with this tag"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about(
+                    "Import watched episodes from another service or from a bingers export\n
+Shows of other services are mapped to TVmaze by their TheTVDB or IMDb id and subscribed.
+bingers: File created by \"bingers export\", merged into the user data (or replacing it)
+trakt: Directory of a Trakt export or its watched-shows.json
+tvtime: Directory of a TV Time (GDPR) export or its seen_episode.csv
+jellyfin: Played episodes on a Jellyfin or Emby server (--url and --api-key, or the server
+configured in config.json, which is also synchronized on every update)
+kodi: Kodi video database (MyVideosNNN.db), marks episodes played in Kodi of subscribed
+shows as watched (requires the sqlite3 command line tool)",
+                )
+                .arg(
+                    Arg::with_name("source")
+                        .required(true)
+                        .index(1)
+                        .possible_values(&["bingers", "trakt", "tvtime", "jellyfin", "kodi"])
+                        .help("Service to import from"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .required_ifs(&[
+                            ("source", "bingers"),
+                            ("source", "trakt"),
+                            ("source", "tvtime"),
+                            ("source", "kodi"),
+                        ])
+                        .index(2)
+                        .help("Exported data"),
+                )
+                .arg(
+                    Arg::with_name("url")
+                        .long("url")
+                        .takes_value(true)
+                        .requires("api_key")
+                        .help("URL of the Jellyfin server (jellyfin only)"),
+                )
+                .arg(
+                    Arg::with_name("api_key")
+                        .long("api-key")
+                        .takes_value(true)
+                        .requires("url")
+                        .help("API key of the Jellyfin server (jellyfin only)"),
+                )
+                .arg(
+                    Arg::with_name("user")
+                        .long("user")
+                        .takes_value(true)
+                        .help("Jellyfin user, if the server has more than one (jellyfin only)"),
+                )
+                .arg(
+                    Arg::with_name("dry_run")
+                        .short("n")
+                        .long("dry-run")
+                        .help("Only report what would be imported"),
+                )
+                .arg(
+                    Arg::with_name("replace")
+                        .long("replace")
+                        .help("Replace the user data instead of merging (bingers only)"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("library")
+                .about("Track which unwatched episodes are available in a local media library")
+                .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("scan")
+                        .about(
+                            "Scan a directory for video files of unwatched episodes\n
+File names like Show.Name.S02E05.mkv, Show Name 2x05.mkv and Show.Name.2026.03.02.mkv
+are recognized. Episodes found are marked in the episode list.",
+                        )
+                        .arg(
+                            Arg::with_name("directory")
+                                .required(true)
+                                .index(1)
+                                .help("Directory of the media library"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("missing")
+                        .about("List aired, unwatched episodes that weren't found in the last scan")
+                        .arg(
+                            Arg::with_name("tag")
+                                .short("t")
+                                .long("tag")
+                                .takes_value(true)
+                                .help("Only list shows with this tag"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("merge")
+                .about(
+                    "Merge user data from another machine\n
+The furthest last watched episode of each show wins, watched episodes and history are combined.
+Shows subscribed on one side only and different viewing states are resolved interactively.",
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .index(1)
+                        .help("user_data.json of the other machine"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sync")
+                .about(
+                    "Synchronize user data with other machines\n
+Commits the user data into a git working copy, merges changes of its upstream branch
+(like the merge command) and pushes the result. Alternatively, merges and uploads the
+user data to the WebDAV folder configured in config.json.",
+                )
+                .arg(
+                    Arg::with_name("git")
+                        .long("git")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .required_unless("webdav")
+                        .help("git working copy with an upstream branch"),
+                )
+                .arg(
+                    Arg::with_name("webdav")
+                        .long("webdav")
+                        .conflicts_with("git")
+                        .help("Synchronize with the configured WebDAV folder"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Print all user data in a portable format (see docs/export-format.md)")
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["bingers", "trakt"])
+                        .default_value("bingers")
+                        .help(
+                            "Export all user data, or only the watch history as body of \
+                             Trakt's history sync (POST /sync/history)",
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("next")
+                .about("List the next unwatched episode of each show")
+                .arg(
+                    Arg::with_name("sort")
+                        .short("s")
+                        .long("sort")
+                        .takes_value(true)
+                        .possible_values(&["activity", "air-date", "priority"])
+                        .default_value("activity")
+                        .help("Sort shows by last watched episode, air date or priority"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .short("t")
+                        .long("tag")
+                        .takes_value(true)
+                        .help("Only list shows with this tag"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("pick")
+                .about(
+                    "Suggest unwatched episodes that fit into the available time\n
+Episodes of a show are suggested in order, otherwise oldest episodes come first.",
+                )
+                .arg(
+                    Arg::with_name("minutes")
+                        .short("m")
+                        .long("minutes")
+                        .takes_value(true)
+                        .value_name("DURATION")
+                        .required(true)
+                        .help("Available time in minutes, or e.g. 2h"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .short("t")
+                        .long("tag")
+                        .takes_value(true)
+                        .help("Only suggest episodes of shows with this tag"),
+                )
+                .arg(
+                    Arg::with_name("random")
+                        .short("r")
+                        .long("random")
+                        .help("Pick shows at random instead of oldest episodes first"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Print viewing statistics from the watch history")
+                .arg(
+                    Arg::with_name("year")
+                        .short("y")
+                        .long("year")
+                        .takes_value(true)
+                        .help("Only include episodes watched in this year"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .short("j")
+                        .long("json")
+                        .help("Print statistics as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("state")
+                .about(
+                    "Set viewing state of show\n
+Episodes of paused and dropped shows are not listed and not updated.",
+                )
+                .arg(
+                    Arg::with_name("tv_show")
+                        .required(true)
+                        .index(1)
+                        .value_name("SHOW"),
+                )
+                .arg(
+                    Arg::with_name("state")
+                        .required(true)
+                        .index(2)
+                        .value_name("STATE")
+                        .possible_values(&[
+                            "watching",
+                            "paused",
+                            "dropped",
+                            "completed",
+                            "plan-to-watch",
+                        ]),
+                )
+                .arg(
+                    Arg::with_name("reason")
+                        .long("reason")
+                        .takes_value(true)
+                        .help("Reason for dropping the show"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("ratings")
+                .about("Show ratings of a show (per-season averages, best and worst episodes)")
+                .arg(
+                    Arg::with_name("tv_show")
+                        .required(true)
+                        .index(1)
+                        .value_name("SHOW"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("unwatched")
+                .about(
+                    "Mark episode as unwatched\n
+If not specified otherwise, will mark the most recently watched episode as unwatched.
+Use the --season and --episode arguments to override.",
+                )
+                .arg(
+                    Arg::with_name("tv_show")
+                        .required(true)
+                        .index(1)
+                        .value_name("SHOW"),
+                )
+                .arg(
+                    Arg::with_name("season")
+                        .short("s")
+                        .long("season")
+                        .takes_value(true)
+                        .help(
+                            "Specify season. \
+                             If used without --episode, will mark whole season as unwatched.",
+                        ),
+                )
                 .arg(
                     Arg::with_name("episode")
                         .short("e")
@@ -159,13 +907,144 @@ Use the --season and --episode arguments to override.",
                         .short("f")
                         .long("force")
                         .help("Force update of all shows and episodes"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .short("t")
+                        .long("tag")
+                        .takes_value(true)
+                        .help("Only update shows with this tag"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("digest")
+                .about(
+                    "Send digest email\n
+Contains episodes aired since the last digest, episodes airing within the next week
+and shows whose status has changed. Run update beforehand to pick up new episodes.",
+                )
+                .arg(
+                    Arg::with_name("smtp")
+                        .long("smtp")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("HOST:PORT")
+                        .help("SMTP server to send the digest through"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("ADDRESS")
+                        .help("Recipient of the digest"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .default_value("bingers@localhost")
+                        .value_name("ADDRESS")
+                        .help("Sender of the digest"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .short("t")
+                        .long("tag")
+                        .takes_value(true)
+                        .help(
+                            "Only include shows with this tag (the digest counts as sent for \
+                             all shows)",
+                        ),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("daemon")
+                .about(
+                    "Run in the background and update periodically\n
+Writes its status to daemon_status.json in the user data directory.
+Stops on SIGTERM or SIGINT. Send SIGUSR1 or use --refresh to update immediately.",
+                )
+                .arg(
+                    Arg::with_name("interval")
+                        .short("i")
+                        .long("interval")
+                        .takes_value(true)
+                        .default_value("6h")
+                        .value_name("DURATION")
+                        .help("Time between updates (e.g. 90m, 6h, 1d)"),
+                )
+                .arg(
+                    Arg::with_name("jitter")
+                        .short("j")
+                        .long("jitter")
+                        .takes_value(true)
+                        .default_value("10m")
+                        .value_name("DURATION")
+                        .help("Maximum random delay added to the interval"),
+                )
+                .arg(
+                    Arg::with_name("hook")
+                        .long("hook")
+                        .takes_value(true)
+                        .value_name("COMMAND")
+                        .help(
+                            "Shell command to run when new episodes are found. \
+                             The number of new episodes is passed in BINGERS_NEW_EPISODES.",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("refresh")
+                        .long("refresh")
+                        .help("Ask the running daemon to update immediately"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about(
+                    "Serve shows and episodes over HTTP\n
+Endpoints (JSON):
+    GET  /shows                       List subscribed shows
+    GET  /episodes                    List unwatched episodes
+    POST /shows/ID/watched[?season=S[&episode=E]]
+    POST /shows/ID/unwatched[?season=S[&episode=E]]
+    POST /update                      Update shows and episodes",
+                )
+                .arg(
+                    Arg::with_name("listen")
+                        .short("l")
+                        .long("listen")
+                        .takes_value(true)
+                        .default_value("127.0.0.1:8080")
+                        .value_name("ADDRESS")
+                        .help("Address to listen on"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("tui").about(
+            "Browse shows and episodes interactively
+
+Keys:
+    tab, h/l    Switch between panes
+    j/k         Move selection
+    enter       Show episodes of selected show
+    w           Mark selection as watched
+    u           Mark selection as unwatched
+    r           Update shows and episodes
+    q           Quit",
+        ))
+        .subcommand(
+            SubCommand::with_name("shell")
+                .about("Run commands interactively (type \"exit\" or press Ctrl-D to quit)"),
+        )
         .after_help(
             "CREDITS:
     Data provided by TVmaze.com\n",
         )
-        .get_matches();
+}
+
+fn main() {
+    // Parse arguments
+    let matches = build_cli().get_matches();
 
     // Run app
     if let Err(ref e) = run(&matches) {
@@ -0,0 +1,211 @@
+use std::cmp::{max, Ordering};
+
+use chrono::{Duration, NaiveDate};
+
+use tvmaze_api::Episode;
+
+/// Episodes scheduled for a single day.
+pub struct PlannedDay<'a> {
+    pub date: NaiveDate,
+    pub episodes: Vec<&'a Episode>,
+    pub runtime: usize,
+}
+
+/// Distributes episodes (together with their runtime in minutes) over consecutive days,
+/// starting at `start`. Episodes are kept in the given order and no more than `per_day`
+/// minutes are scheduled per day, except for episodes that are longer than that on their own.
+pub fn schedule<'a>(
+    episodes: &[(&'a Episode, usize)],
+    start: NaiveDate,
+    per_day: usize,
+) -> Vec<PlannedDay<'a>> {
+    let mut days: Vec<PlannedDay<'a>> = Vec::new();
+
+    for &(episode, runtime) in episodes {
+        let fits = match days.last() {
+            Some(day) => day.runtime + runtime <= per_day,
+            None => false,
+        };
+
+        if !fits {
+            let date = match days.last() {
+                Some(day) => day.date + Duration::days(1),
+                None => start,
+            };
+            days.push(PlannedDay {
+                date,
+                episodes: Vec::new(),
+                runtime: 0,
+            });
+        }
+
+        if let Some(day) = days.last_mut() {
+            day.episodes.push(episode);
+            day.runtime += runtime;
+        }
+    }
+
+    days
+}
+
+/// Smallest number of minutes per day that is needed to watch all episodes from `start`
+/// until `until` (inclusive). Returns `None` if `until` lies before `start`.
+pub fn minimum_per_day(
+    episodes: &[(&Episode, usize)],
+    start: NaiveDate,
+    until: NaiveDate,
+) -> Option<usize> {
+    let available_days = (until - start).num_days() + 1;
+    if available_days < 1 {
+        return None;
+    }
+    let available_days = available_days as usize;
+
+    let total: usize = episodes.iter().map(|&(_, runtime)| runtime).sum();
+
+    let longest = episodes
+        .iter()
+        .map(|&(_, runtime)| runtime)
+        .max()
+        .unwrap_or(0);
+
+    // More minutes per day never need more days, so search for the smallest value that fits
+    let mut low = max(total.div_ceil(available_days), longest);
+    let mut high = total;
+    while low < high {
+        let per_day = (low + high) / 2;
+        if schedule(episodes, start, per_day).len() <= available_days {
+            high = per_day;
+        } else {
+            low = per_day + 1;
+        }
+    }
+
+    Some(low)
+}
+
+/// Picks episodes that fit into the given number of minutes.
+///
+/// Only the next unwatched episode of each show is eligible, so that episodes of a show are
+/// picked in order. Among the eligible episodes that fit, `choose` selects one by its index,
+/// eligible episodes being ordered like `episodes` (e.g. oldest first).
+pub fn pick<'a, F>(
+    episodes: &[(&'a Episode, usize)],
+    minutes: usize,
+    mut choose: F,
+) -> Vec<&'a Episode>
+where
+    F: FnMut(usize) -> usize,
+{
+    let mut remaining: Vec<(&Episode, usize)> = episodes.to_vec();
+    let mut minutes_left = minutes;
+    let mut picked = Vec::new();
+
+    loop {
+        let candidates: Vec<usize> = remaining
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(episode, runtime))| {
+                runtime <= minutes_left
+                    && !remaining.iter().any(|&(other, _)| {
+                        other.show_id == episode.show_id && other.cmp(episode) == Ordering::Less
+                    })
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        if candidates.is_empty() {
+            break;
+        }
+
+        let (episode, runtime) = remaining.remove(candidates[choose(candidates.len())]);
+        minutes_left -= runtime;
+        picked.push(episode);
+    }
+
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(number: usize) -> Episode {
+        Episode {
+            episode_id: number,
+            show_id: 20263,
+            name: format!("Episode {}", number),
+            season: 1,
+            number,
+            airstamp: None,
+            runtime: None,
+            watched: false,
+        }
+    }
+
+    #[test]
+    fn schedule_episodes_per_day() {
+        let episodes = [episode(1), episode(2), episode(3), episode(4)];
+        let backlog: Vec<(&Episode, usize)> = episodes
+            .iter()
+            .zip(&[45, 45, 100, 30])
+            .map(|(episode, &runtime)| (episode, runtime))
+            .collect();
+        let start = NaiveDate::from_ymd(2026, 12, 1);
+
+        let days = schedule(&backlog, start, 90);
+        assert_eq!(3, days.len());
+        assert_eq!(
+            vec![1, 2],
+            days[0]
+                .episodes
+                .iter()
+                .map(|e| e.number)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(NaiveDate::from_ymd(2026, 12, 2), days[1].date);
+        assert_eq!(100, days[1].runtime);
+        assert_eq!(30, days[2].runtime);
+
+        assert_eq!(
+            Some(100),
+            minimum_per_day(&backlog, start, NaiveDate::from_ymd(2026, 12, 3))
+        );
+        assert_eq!(
+            Some(130),
+            minimum_per_day(&backlog, start, NaiveDate::from_ymd(2026, 12, 2))
+        );
+        assert_eq!(
+            Some(220),
+            minimum_per_day(&backlog, start, NaiveDate::from_ymd(2026, 12, 1))
+        );
+        assert_eq!(
+            None,
+            minimum_per_day(&backlog, start, NaiveDate::from_ymd(2026, 11, 30))
+        );
+    }
+
+    #[test]
+    fn pick_episodes_in_order() {
+        let mut episodes = [episode(4), episode(5), episode(6)];
+        episodes[2].show_id = 7480;
+        episodes[2].number = 1;
+
+        // Oldest first: S01E05 is listed first, but S01E04 of the same show must come first
+        let backlog = vec![(&episodes[1], 45), (&episodes[2], 60), (&episodes[0], 40)];
+
+        let picked: Vec<(usize, usize)> = pick(&backlog, 90, |_| 0)
+            .iter()
+            .map(|episode| (episode.show_id, episode.number))
+            .collect();
+        assert_eq!(vec![(7480, 1)], picked);
+
+        let picked: Vec<(usize, usize)> = pick(&backlog, 90, |candidates| candidates - 1)
+            .iter()
+            .map(|episode| (episode.show_id, episode.number))
+            .collect();
+        assert_eq!(vec![(20263, 4), (20263, 5)], picked);
+
+        assert!(pick(&backlog, 30, |_| 0).is_empty());
+    }
+}
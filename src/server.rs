@@ -0,0 +1,334 @@
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::thread;
+
+use futures::sync::oneshot;
+use futures::{future, Future};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::service::service_fn;
+use hyper::{self, Body, Method, Request, Response, Server, StatusCode};
+
+use serde::Serialize;
+
+use app::App;
+use errors::*;
+
+/// Requests that are forwarded from the HTTP server to the thread owning the `App`.
+#[derive(Debug, PartialEq)]
+enum ApiRequest {
+    ListShows,
+    ListUnwatched,
+    MarkAsWatched(usize, Option<usize>, Option<usize>),
+    MarkAsUnwatched(usize, Option<usize>, Option<usize>),
+    Update,
+}
+
+#[derive(Debug)]
+struct ApiResponse {
+    status: StatusCode,
+    body: String,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+}
+
+#[derive(Serialize)]
+struct MarkedBody {
+    season: usize,
+    episode: usize,
+}
+
+#[derive(Serialize)]
+struct UpdateBody {
+    new_episodes: usize,
+}
+
+impl ApiResponse {
+    fn json<T: Serialize>(value: &T) -> ApiResponse {
+        match ::serde_json::to_string(value) {
+            Ok(body) => ApiResponse {
+                status: StatusCode::OK,
+                body,
+            },
+            Err(e) => ApiResponse::error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        }
+    }
+
+    fn error(status: StatusCode, message: &str) -> ApiResponse {
+        ApiResponse {
+            status,
+            body: ::serde_json::to_string(&ErrorBody { error: message })
+                .unwrap_or_else(|_| "{}".to_string()),
+        }
+    }
+
+    fn marked(marked: Option<(usize, usize)>) -> ApiResponse {
+        match marked {
+            Some((season, episode)) => ApiResponse::json(&MarkedBody { season, episode }),
+            None => ApiResponse::error(StatusCode::NOT_FOUND, "No matching episode found"),
+        }
+    }
+
+    fn into_response(self) -> Response<Body> {
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = self.status;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        response
+    }
+}
+
+type Job = (ApiRequest, oneshot::Sender<ApiResponse>);
+
+/// Parses `season` and `episode` from the query string.
+fn parse_episode_query(
+    query: Option<&str>,
+) -> ::std::result::Result<(Option<usize>, Option<usize>), ApiResponse> {
+    let mut season = None;
+    let mut episode = None;
+
+    for pair in query
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+    {
+        let mut pair = pair.splitn(2, '=');
+        let key = pair.next().unwrap_or("");
+        let value = pair.next().unwrap_or("");
+
+        let value = match value.parse::<usize>() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                return Err(ApiResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid value for {} [{}]", key, value),
+                ))
+            }
+        };
+
+        match key {
+            "season" => season = value,
+            "episode" => episode = value,
+            _ => {
+                return Err(ApiResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Unknown parameter [{}]", key),
+                ))
+            }
+        }
+    }
+
+    if episode.is_some() && season.is_none() {
+        return Err(ApiResponse::error(
+            StatusCode::BAD_REQUEST,
+            "Parameter episode requires season",
+        ));
+    }
+
+    Ok((season, episode))
+}
+
+fn parse_request(
+    method: &Method,
+    path: &str,
+    query: Option<&str>,
+) -> ::std::result::Result<ApiRequest, ApiResponse> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    match (method, segments.as_slice()) {
+        (&Method::GET, ["shows"]) => Ok(ApiRequest::ListShows),
+        (&Method::GET, ["episodes"]) => Ok(ApiRequest::ListUnwatched),
+        (&Method::POST, ["update"]) => Ok(ApiRequest::Update),
+        (&Method::POST, ["shows", show_id, action]) => {
+            let show_id = match show_id.parse::<usize>() {
+                Ok(show_id) => show_id,
+                Err(_) => {
+                    return Err(ApiResponse::error(
+                        StatusCode::BAD_REQUEST,
+                        &format!("Invalid show id [{}]", show_id),
+                    ))
+                }
+            };
+            let (season, episode) = parse_episode_query(query)?;
+
+            match *action {
+                "watched" => Ok(ApiRequest::MarkAsWatched(show_id, season, episode)),
+                "unwatched" => Ok(ApiRequest::MarkAsUnwatched(show_id, season, episode)),
+                _ => Err(ApiResponse::error(StatusCode::NOT_FOUND, "Not found")),
+            }
+        }
+        _ => Err(ApiResponse::error(StatusCode::NOT_FOUND, "Not found")),
+    }
+}
+
+/// Forwards a request to the `App` thread and waits (asynchronously) for the response.
+fn handle_request(
+    request: &Request<Body>,
+    jobs: &mpsc::Sender<Job>,
+) -> Box<dyn Future<Item = Response<Body>, Error = hyper::Error> + Send> {
+    println!("{} {}", request.method(), request.uri());
+
+    let api_request = match parse_request(
+        request.method(),
+        request.uri().path(),
+        request.uri().query(),
+    ) {
+        Ok(api_request) => api_request,
+        Err(response) => return Box::new(future::ok(response.into_response())),
+    };
+
+    let (sender, receiver) = oneshot::channel();
+    if jobs.send((api_request, sender)).is_err() {
+        return Box::new(future::ok(
+            ApiResponse::error(StatusCode::SERVICE_UNAVAILABLE, "Shutting down").into_response(),
+        ));
+    }
+
+    Box::new(receiver.then(|response| {
+        let response = match response {
+            Ok(response) => response,
+            Err(_) => ApiResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Request cancelled"),
+        };
+
+        Ok(response.into_response())
+    }))
+}
+
+fn is_subscribed(app: &App, show_id: usize) -> bool {
+    app.user_data()
+        .subscribed_shows()
+        .iter()
+        .any(|show| show.id == show_id)
+}
+
+fn process_request(app: &mut App, request: ApiRequest) -> Result<ApiResponse> {
+    // Other commands may have changed the user data since the last request
+    app.reload()?;
+
+    let response = match request {
+        ApiRequest::ListShows => {
+            ApiResponse::json(&app.user_data().subscribed_shows_by_most_recent())
+        }
+        ApiRequest::ListUnwatched => {
+            let episodes: Vec<_> = app
+                .user_data()
+                .unwatched_episodes_oldest_first()
+                .into_iter()
+                .filter(|episode| !episode.watched)
+                .collect();
+            ApiResponse::json(&episodes)
+        }
+        ApiRequest::MarkAsWatched(show_id, season, episode) => {
+            if !is_subscribed(app, show_id) {
+                return Ok(ApiResponse::error(StatusCode::NOT_FOUND, "Show not found"));
+            }
+            ApiResponse::marked(app.mark_as_watched_by_id(show_id, season, episode)?)
+        }
+        ApiRequest::MarkAsUnwatched(show_id, season, episode) => {
+            if !is_subscribed(app, show_id) {
+                return Ok(ApiResponse::error(StatusCode::NOT_FOUND, "Show not found"));
+            }
+            ApiResponse::marked(app.mark_as_unwatched_by_id(show_id, season, episode)?)
+        }
+        ApiRequest::Update => ApiResponse::json(&UpdateBody {
+            new_episodes: app.update(false, None)?,
+        }),
+    };
+
+    Ok(response)
+}
+
+/// Serves the user data over a small JSON API until the server fails.
+///
+/// The HTTP server runs on its own thread. Requests are handed over to the calling thread,
+/// which owns the `App` and processes them one at a time, so that user data is modified and
+/// stored exactly like with the command line interface. The user data is reloaded for every
+/// request, so that changes made by other commands in the meantime aren't overwritten.
+pub fn run(app: &mut App, addr: &SocketAddr) -> Result<()> {
+    let (jobs, receiver) = mpsc::channel::<Job>();
+
+    let server = Server::try_bind(addr)
+        .chain_err(|| format!("Unable to listen on {}", addr))?
+        .serve(move || {
+            let jobs = jobs.clone();
+            service_fn(move |request| handle_request(&request, &jobs))
+        });
+
+    println!("Listening on http://{}", server.local_addr());
+
+    thread::spawn(move || {
+        hyper::rt::run(server.map_err(|e| println!("Server error: {}", e)));
+    });
+
+    for (request, sender) in receiver {
+        let response = match process_request(app, request) {
+            Ok(response) => response,
+            Err(e) => ApiResponse::error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        };
+
+        // Client might have disconnected in the meantime
+        let _ = sender.send(response);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_routes() {
+        assert_eq!(
+            ApiRequest::ListShows,
+            parse_request(&Method::GET, "/shows", None).unwrap()
+        );
+        assert_eq!(
+            ApiRequest::ListUnwatched,
+            parse_request(&Method::GET, "/episodes/", None).unwrap()
+        );
+        assert_eq!(
+            ApiRequest::Update,
+            parse_request(&Method::POST, "/update", None).unwrap()
+        );
+        assert_eq!(
+            ApiRequest::MarkAsWatched(20263, None, None),
+            parse_request(&Method::POST, "/shows/20263/watched", None).unwrap()
+        );
+        assert_eq!(
+            ApiRequest::MarkAsUnwatched(20263, Some(1), Some(5)),
+            parse_request(
+                &Method::POST,
+                "/shows/20263/unwatched",
+                Some("season=1&episode=5")
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn reject_invalid_requests() {
+        let status = |method, path, query| parse_request(method, path, query).unwrap_err().status;
+
+        assert_eq!(StatusCode::NOT_FOUND, status(&Method::POST, "/shows", None));
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            status(&Method::GET, "/shows/1/watched", None)
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            status(&Method::POST, "/shows/orville/watched", None)
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            status(&Method::POST, "/shows/1/watched", Some("episode=5"))
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            status(&Method::POST, "/shows/1/watched", Some("season=one"))
+        );
+    }
+}
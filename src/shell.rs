@@ -0,0 +1,260 @@
+use std::borrow::Cow;
+use std::fs;
+
+use error_chain::ChainedError;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use shell_words;
+
+use app::App;
+use errors::*;
+use user_data;
+
+const HISTORY_FILE: &str = "shell_history.txt";
+
+/// Completes subcommand names and names of subscribed shows.
+struct ShellHelper {
+    subcommands: Vec<String>,
+    show_names: Vec<String>,
+}
+
+impl ShellHelper {
+    fn new() -> Self {
+        let mut subcommands: Vec<String> = ::SUBCOMMANDS
+            .iter()
+            .map(|subcommand| subcommand.to_string())
+            .collect();
+        subcommands.extend(vec!["exit".to_string(), "help".to_string()]);
+
+        Self {
+            subcommands,
+            show_names: Vec::new(),
+        }
+    }
+}
+
+/// Quotes a show name such that it is parsed as a single argument.
+fn quote(name: &str) -> Cow<'_, str> {
+    if name.contains(char::is_whitespace) || name.contains('\'') || name.contains('"') {
+        shell_words::quote(name)
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// Returns the candidates for the word that ends at `pos`, along with the start of that word.
+fn complete(
+    line: &str,
+    pos: usize,
+    subcommands: &[String],
+    show_names: &[String],
+) -> (usize, Vec<String>) {
+    let line = &line[..pos];
+
+    // The first word is the subcommand, every other word could be a show name. Show names
+    // can contain spaces, so they are completed from the first quote or the first word after
+    // the subcommand.
+    let start = match line.find(char::is_whitespace) {
+        Some(end_of_subcommand) => {
+            let rest = &line[end_of_subcommand..];
+            end_of_subcommand + (rest.len() - rest.trim_start().len())
+        }
+        None => {
+            let candidates = subcommands
+                .iter()
+                .filter(|subcommand| subcommand.starts_with(line))
+                .cloned()
+                .collect();
+            return (0, candidates);
+        }
+    };
+
+    let prefix = line[start..]
+        .trim_start_matches(&['"', '\''][..])
+        .to_lowercase();
+    if prefix.starts_with('-') {
+        return (start, Vec::new());
+    }
+
+    let candidates = show_names
+        .iter()
+        .filter(|name| name.to_lowercase().starts_with(&prefix))
+        .map(|name| quote(name).into_owned())
+        .collect();
+
+    (start, candidates)
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context,
+    ) -> ::rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, candidates) = complete(line, pos, &self.subcommands, &self.show_names);
+
+        Ok((
+            start,
+            candidates
+                .into_iter()
+                .map(|candidate| Pair {
+                    display: candidate.clone(),
+                    replacement: candidate,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Parses and runs a single line. Returns false if the shell should exit.
+fn run_line(app: &mut App, line: &str) -> Result<bool> {
+    let words = match shell_words::split(line) {
+        Ok(words) => words,
+        Err(e) => bail!("Unable to parse command: {}", e),
+    };
+
+    match words.first().map(|word| word.as_str()) {
+        None => return Ok(true),
+        Some("exit") | Some("quit") => return Ok(false),
+        Some("shell") => bail!("Already running a shell"),
+        _ => {}
+    }
+
+    let args = Some("bingers".to_string()).into_iter().chain(words);
+    match ::build_cli().get_matches_from_safe(args) {
+        Ok(matches) => ::run_subcommand(app, &matches)?,
+        // Also used for --help and --version
+        Err(e) => println!("{}", e.message),
+    }
+
+    Ok(true)
+}
+
+/// Runs an interactive prompt that accepts the same subcommands as the command line.
+///
+/// User data and the connection to the TVmaze API are kept between commands.
+pub fn run(app: &mut App) -> Result<()> {
+    let data_dir = user_data::data_dir()?;
+    let history_file = data_dir.join(HISTORY_FILE);
+
+    let mut editor = Editor::new()?;
+    editor.set_helper(Some(ShellHelper::new()));
+
+    // History doesn't exist on first run
+    let _ = editor.load_history(&history_file);
+
+    loop {
+        if let Some(helper) = editor.helper_mut() {
+            helper.show_names = app
+                .user_data()
+                .subscribed_shows()
+                .iter()
+                .map(|show| show.name.clone())
+                .collect();
+        }
+
+        let line = match editor.readline("bingers> ") {
+            Ok(line) => line,
+            // Ctrl-C discards the current line
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        editor.add_history_entry(line.as_str())?;
+
+        match run_line(app, &line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("{}", e.display_chain()),
+        }
+    }
+
+    fs::create_dir_all(&data_dir)
+        .chain_err(|| format!("Unable to create user data directory {:?}", data_dir))?;
+    editor
+        .save_history(&history_file)
+        .chain_err(|| format!("Unable to save history to {:?}", history_file))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_subcommands_and_show_names() {
+        let subcommands = vec!["watched".to_string(), "unwatched".to_string()];
+        let show_names = vec![
+            "The Orville".to_string(),
+            "Star Trek: Discovery".to_string(),
+        ];
+
+        assert_eq!(
+            (0, vec!["watched".to_string()]),
+            complete("wa", 2, &subcommands, &show_names)
+        );
+        assert_eq!(
+            (8, vec!["'The Orville'".to_string()]),
+            complete("watched the", 11, &subcommands, &show_names)
+        );
+        assert_eq!(
+            (10, vec!["'Star Trek: Discovery'".to_string()]),
+            complete("unwatched \"Star T", 17, &subcommands, &show_names)
+        );
+        assert_eq!(
+            (8, Vec::<String>::new()),
+            complete("watched -s", 10, &subcommands, &show_names)
+        );
+    }
+
+    #[test]
+    fn list_all_subcommands() {
+        // Every listed subcommand exists...
+        for subcommand in ::SUBCOMMANDS.iter() {
+            let result = ::build_cli().get_matches_from_safe(vec!["bingers", subcommand, "--help"]);
+            assert_eq!(
+                ::clap::ErrorKind::HelpDisplayed,
+                result.unwrap_err().kind,
+                "{}",
+                subcommand
+            );
+        }
+
+        // ...and every subcommand in the help is listed
+        let mut help = Vec::new();
+        ::build_cli().write_help(&mut help).unwrap();
+        let help = String::from_utf8(help).unwrap();
+        let subcommands: Vec<&str> = help
+            .lines()
+            .skip_while(|line| !line.starts_with("SUBCOMMANDS:"))
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            // Descriptions that don't fit on one line continue with a deeper indentation
+            .filter(|line| line.starts_with("    ") && !line.starts_with("     "))
+            .filter_map(|line| line.split_whitespace().next())
+            .filter(|subcommand| *subcommand != "help")
+            .collect();
+        assert!(!subcommands.is_empty());
+        for subcommand in subcommands {
+            assert!(::SUBCOMMANDS.contains(&subcommand), "{}", subcommand);
+        }
+    }
+}
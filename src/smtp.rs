@@ -0,0 +1,231 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use errors::*;
+
+const BOUNDARY: &str = "bingers-digest-boundary";
+
+/// A plain-text email with an HTML alternative.
+pub struct Mail<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    pub subject: &'a str,
+    pub text: &'a str,
+    pub html: &'a str,
+}
+
+impl<'a> Mail<'a> {
+    /// Formats the mail as a MIME multipart/alternative message (with CRLF line endings).
+    fn to_message(&self) -> String {
+        let mut message = String::new();
+
+        message.push_str(&format!("From: {}\r\n", self.from));
+        message.push_str(&format!("To: {}\r\n", self.to));
+        message.push_str(&format!("Subject: {}\r\n", self.subject));
+        message.push_str(&format!("Date: {}\r\n", Utc::now().to_rfc2822()));
+        message.push_str("MIME-Version: 1.0\r\n");
+        message.push_str(&format!(
+            "Content-Type: multipart/alternative; boundary=\"{}\"\r\n",
+            BOUNDARY
+        ));
+        message.push_str("\r\n");
+
+        for &(content_type, body) in &[("text/plain", self.text), ("text/html", self.html)] {
+            message.push_str(&format!("--{}\r\n", BOUNDARY));
+            message.push_str(&format!(
+                "Content-Type: {}; charset=utf-8\r\n",
+                content_type
+            ));
+            message.push_str("Content-Transfer-Encoding: 8bit\r\n");
+            message.push_str("\r\n");
+
+            for line in body.lines() {
+                // Dot-stuffing (RFC 5321, section 4.5.2)
+                if line.starts_with('.') {
+                    message.push('.');
+                }
+                message.push_str(line);
+                message.push_str("\r\n");
+            }
+        }
+
+        message.push_str(&format!("--{}--\r\n", BOUNDARY));
+
+        message
+    }
+}
+
+struct SmtpConnection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl SmtpConnection {
+    fn connect(server: &str) -> Result<Self> {
+        let stream = TcpStream::connect(server)
+            .chain_err(|| format!("Unable to connect to SMTP server [{}]", server))?;
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+        stream.set_write_timeout(Some(Duration::from_secs(30)))?;
+
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    /// Reads a (possibly multi-line) reply and checks that it has the expected status code.
+    fn expect_reply(&mut self, expected: u16) -> Result<()> {
+        let mut reply = String::new();
+
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(ErrorKind::SmtpError("Connection closed".to_string()).into());
+            }
+            reply.push_str(&line);
+
+            // Continuation lines have a '-' after the status code
+            if line.len() < 4 || line.as_bytes()[3] != b'-' {
+                break;
+            }
+        }
+
+        let code = reply.get(0..3).and_then(|code| code.parse::<u16>().ok());
+        if code != Some(expected) {
+            return Err(ErrorKind::SmtpError(reply.trim().to_string()).into());
+        }
+
+        Ok(())
+    }
+
+    fn command(&mut self, command: &str, expected: u16) -> Result<()> {
+        self.writer.write_all(command.as_bytes())?;
+        self.writer.write_all(b"\r\n")?;
+        self.expect_reply(expected)
+    }
+}
+
+/// Sends a mail through the given SMTP server (`host:port`).
+///
+/// Only plain, unauthenticated SMTP is supported, which is enough for a local relay.
+pub fn send_mail(server: &str, mail: &Mail) -> Result<()> {
+    let mut connection = SmtpConnection::connect(server)?;
+
+    connection.expect_reply(220)?;
+    connection.command("EHLO localhost", 250)?;
+    connection.command(&format!("MAIL FROM:<{}>", mail.from), 250)?;
+    connection.command(&format!("RCPT TO:<{}>", mail.to), 250)?;
+    connection.command("DATA", 354)?;
+
+    connection.writer.write_all(mail.to_message().as_bytes())?;
+    connection.command(".", 250)?;
+
+    connection.command("QUIT", 221)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    /// Minimal SMTP server that accepts a single mail and returns the received DATA section.
+    fn smtp_stand_in(listener: TcpListener) -> String {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+
+        writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+
+        let mut data = String::new();
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+
+            if in_data {
+                if line == ".\r\n" {
+                    in_data = false;
+                    writer.write_all(b"250 OK\r\n").unwrap();
+                } else {
+                    data.push_str(&line);
+                }
+                continue;
+            }
+
+            if line.starts_with("EHLO") {
+                writer
+                    .write_all(b"250-localhost\r\n250 8BITMIME\r\n")
+                    .unwrap();
+            } else if line.starts_with("DATA") {
+                in_data = true;
+                writer.write_all(b"354 Go ahead\r\n").unwrap();
+            } else if line.starts_with("QUIT") {
+                writer.write_all(b"221 Bye\r\n").unwrap();
+                break;
+            } else {
+                writer.write_all(b"250 OK\r\n").unwrap();
+            }
+        }
+
+        data
+    }
+
+    #[test]
+    fn send_mail_to_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || smtp_stand_in(listener));
+
+        let mail = Mail {
+            from: "bingers@localhost",
+            to: "me@localhost",
+            subject: "Test",
+            text: "Hello\n.hidden dot",
+            html: "<p>Hello</p>",
+        };
+        send_mail(&server, &mail).unwrap();
+
+        let data = handle.join().unwrap();
+        assert!(data.contains("To: me@localhost\r\n"));
+        assert!(data.contains("Subject: Test\r\n"));
+        assert!(data.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+        assert!(data.contains("Hello\r\n..hidden dot\r\n"));
+        assert!(data.contains("<p>Hello</p>\r\n"));
+    }
+
+    #[test]
+    fn report_unexpected_reply() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"554 No SMTP service here\r\n").unwrap();
+        });
+
+        let mail = Mail {
+            from: "bingers@localhost",
+            to: "me@localhost",
+            subject: "Test",
+            text: "",
+            html: "",
+        };
+        match send_mail(&server, &mail) {
+            Err(Error(ErrorKind::SmtpError(reply), _)) => {
+                assert_eq!("554 No SMTP service here", reply)
+            }
+            _ => panic!("Expected SMTP error"),
+        }
+
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{Datelike, Duration, NaiveDate, TimeZone};
+
+use app::format_runtime;
+use tvmaze_api::{Episode, Show, Status, ViewingState};
+use user_data::WatchedEpisode;
+
+/// Number of episodes and minutes watched of a show or on a network.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Breakdown {
+    pub name: String,
+    pub episodes: usize,
+    pub minutes: usize,
+}
+
+/// Viewing statistics, built from the watch history.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub year: Option<i32>,
+    pub episodes: usize,
+    pub minutes: usize,
+    pub shows: Vec<Breakdown>,
+    pub networks: Vec<Breakdown>,
+    pub busiest_weekday: Option<String>,
+    pub longest_streak: usize,
+    pub finished_shows: Vec<String>,
+}
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Adds an episode to the breakdown of the given name.
+fn add_to(breakdowns: &mut Vec<Breakdown>, name: &str, minutes: usize) {
+    match breakdowns
+        .iter_mut()
+        .find(|breakdown| breakdown.name == name)
+    {
+        Some(breakdown) => {
+            breakdown.episodes += 1;
+            breakdown.minutes += minutes;
+        }
+        None => breakdowns.push(Breakdown {
+            name: name.to_string(),
+            episodes: 1,
+            minutes,
+        }),
+    }
+}
+
+/// Most days in a row on which at least one episode has been watched.
+fn longest_streak(dates: &BTreeSet<NaiveDate>) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for &date in dates {
+        current = match previous {
+            Some(previous) if previous + Duration::days(1) == date => current + 1,
+            _ => 1,
+        };
+        if current > longest {
+            longest = current;
+        }
+        previous = Some(date);
+    }
+
+    longest
+}
+
+impl Stats {
+    /// Calculates the statistics of the given year (or of all time), with days and weekdays
+    /// in the given time zone.
+    ///
+    /// A show counts as finished if it has been completed or has ended without unwatched
+    /// episodes left, and its last episode has been watched within the year.
+    pub fn new<Tz: TimeZone>(
+        history: &[WatchedEpisode],
+        shows: &[Show],
+        unwatched_episodes: &[Episode],
+        year: Option<i32>,
+        timezone: &Tz,
+    ) -> Stats {
+        let show_by_id: HashMap<usize, &Show> = shows.iter().map(|show| (show.id, show)).collect();
+
+        let mut stats = Stats {
+            year,
+            episodes: 0,
+            minutes: 0,
+            shows: Vec::new(),
+            networks: Vec::new(),
+            busiest_weekday: None,
+            longest_streak: 0,
+            finished_shows: Vec::new(),
+        };
+        let mut dates = BTreeSet::new();
+        let mut weekdays = [0; 7];
+        let mut last_watched: HashMap<usize, NaiveDate> = HashMap::new();
+
+        for entry in history {
+            let date = entry.watched.with_timezone(timezone).naive_local().date();
+            let last = last_watched.entry(entry.show_id).or_insert(date);
+            if date > *last {
+                *last = date;
+            }
+
+            if year.is_some() && Some(date.year()) != year {
+                continue;
+            }
+
+            let show = show_by_id.get(&entry.show_id);
+            let minutes = entry
+                .runtime
+                .or_else(|| show.and_then(|show| show.runtime))
+                .unwrap_or(0);
+            let (show_name, network_name) = match show {
+                Some(show) => (show.name.as_str(), show.network_name()),
+                None => ("Unknown", "Unknown"),
+            };
+
+            stats.episodes += 1;
+            stats.minutes += minutes;
+            add_to(&mut stats.shows, show_name, minutes);
+            add_to(&mut stats.networks, network_name, minutes);
+
+            dates.insert(date);
+            weekdays[date.weekday().num_days_from_monday() as usize] += 1;
+        }
+
+        stats
+            .shows
+            .sort_by_key(|breakdown| (Reverse(breakdown.minutes), Reverse(breakdown.episodes)));
+        stats
+            .networks
+            .sort_by_key(|breakdown| (Reverse(breakdown.minutes), Reverse(breakdown.episodes)));
+
+        // Earlier weekdays win ties
+        stats.busiest_weekday = (0..7)
+            .filter(|&day| weekdays[day] > 0)
+            .max_by_key(|&day| (weekdays[day], Reverse(day)))
+            .map(|day| WEEKDAYS[day].to_string());
+        stats.longest_streak = longest_streak(&dates);
+
+        for show in shows {
+            let finished = match show.viewing_state {
+                ViewingState::Completed => true,
+                _ => {
+                    show.status == Status::Ended
+                        && !unwatched_episodes
+                            .iter()
+                            .any(|episode| episode.show_id == show.id && !episode.watched)
+                }
+            };
+            let finished_in_year = match last_watched.get(&show.id) {
+                Some(date) => year.is_none() || Some(date.year()) == year,
+                None => false,
+            };
+
+            if finished && finished_in_year {
+                stats.finished_shows.push(show.name.clone());
+            }
+        }
+
+        stats
+    }
+
+    fn format_breakdowns(title: &str, breakdowns: &[Breakdown]) -> String {
+        let width = breakdowns
+            .iter()
+            .map(|breakdown| breakdown.name.len())
+            .fold(title.len(), |width, length| width.max(length));
+
+        let mut text = format!("{: <width$} | Episodes | Time\n", title, width = width);
+        text.push_str(&format!(
+            "{:-<width$}-|----------|---------\n",
+            "-",
+            width = width
+        ));
+        for breakdown in breakdowns {
+            text.push_str(&format!(
+                "{: <width$} | {: >8} | {}\n",
+                breakdown.name,
+                breakdown.episodes,
+                format_runtime(breakdown.minutes),
+                width = width
+            ));
+        }
+
+        text
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = match self.year {
+            Some(year) => format!("Statistics for {}:\n\n", year),
+            None => "Statistics:\n\n".to_string(),
+        };
+
+        if self.episodes == 0 {
+            text.push_str("No episodes watched.\n");
+            return text;
+        }
+
+        text.push_str(&format!("Episodes watched: {}\n", self.episodes));
+        text.push_str(&format!(
+            "Time watched:     {}\n",
+            format_runtime(self.minutes)
+        ));
+        if let Some(ref weekday) = self.busiest_weekday {
+            text.push_str(&format!("Busiest weekday:  {}\n", weekday));
+        }
+        text.push_str(&format!(
+            "Longest streak:   {} day(s)\n\n",
+            self.longest_streak
+        ));
+
+        text.push_str(&Stats::format_breakdowns("Show", &self.shows));
+        text.push('\n');
+        text.push_str(&Stats::format_breakdowns("Network", &self.networks));
+
+        if !self.finished_shows.is_empty() {
+            text.push_str("\nFinished shows:\n");
+            for show in &self.finished_shows {
+                text.push_str(&format!("    {}\n", show));
+            }
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{DateTime, Utc};
+
+    use tvmaze_api::Network;
+    use user_data::tests::show;
+
+    fn watched(
+        show_id: usize,
+        number: usize,
+        runtime: Option<usize>,
+        watched: &str,
+    ) -> WatchedEpisode {
+        WatchedEpisode {
+            episode_id: show_id * 100 + number,
+            show_id,
+            season: 1,
+            number,
+            name: format!("Episode {}", number),
+            runtime,
+            watched: watched.parse::<DateTime<Utc>>().unwrap(),
+            rating: None,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn statistics_of_year() {
+        let mut the_orville = show(1, "The Orville");
+        the_orville.network = Some(Network {
+            id: 1,
+            name: "FOX".to_string(),
+        });
+        the_orville.status = Status::Ended;
+        let shows = vec![the_orville, show(2, "Star Trek: Discovery")];
+        let history = vec![
+            watched(1, 1, Some(45), "2025-12-31T20:00:00Z"),
+            watched(1, 2, Some(45), "2026-03-02T20:00:00Z"),
+            watched(1, 3, None, "2026-03-03T20:00:00Z"),
+            watched(2, 1, Some(30), "2026-03-03T21:00:00Z"),
+            watched(2, 2, Some(30), "2026-03-05T20:00:00Z"),
+        ];
+
+        let stats = Stats::new(&history, &shows, &[], Some(2026), &Utc);
+        assert_eq!(4, stats.episodes);
+        assert_eq!(165, stats.minutes);
+        assert_eq!(
+            vec![
+                Breakdown {
+                    name: "The Orville".to_string(),
+                    episodes: 2,
+                    minutes: 105,
+                },
+                Breakdown {
+                    name: "Star Trek: Discovery".to_string(),
+                    episodes: 2,
+                    minutes: 60,
+                },
+            ],
+            stats.shows
+        );
+        assert_eq!("Unknown", stats.networks[1].name);
+        assert_eq!(Some("Tuesday".to_string()), stats.busiest_weekday);
+        assert_eq!(2, stats.longest_streak);
+        assert_eq!(vec!["The Orville".to_string()], stats.finished_shows);
+
+        let stats = Stats::new(&history, &shows, &[], Some(2025), &Utc);
+        assert_eq!(1, stats.episodes);
+        assert!(stats.finished_shows.is_empty());
+
+        let stats = Stats::new(&history, &shows, &[], None, &Utc);
+        assert_eq!(5, stats.episodes);
+    }
+}
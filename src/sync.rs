@@ -0,0 +1,382 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+
+use app::App;
+use config::WebDavConfig;
+use errors::*;
+use user_data::{self, UserData};
+use webdav::{Upload, WebDav};
+
+/// Name of the user data file in the git working copy.
+const USER_DATA_FILE: &str = "user_data.json";
+
+/// User data as of the last WebDAV sync (in the data directory), the base of three-way merges.
+const WEBDAV_BASE_FILE: &str = "webdav_base.json";
+
+/// ETag of the remote file as of the last WebDAV sync (in the data directory).
+const WEBDAV_ETAG_FILE: &str = "webdav_etag";
+
+/// How often to retry a WebDAV sync, if the remote file changes while syncing.
+const WEBDAV_ATTEMPTS: usize = 3;
+
+/// Runs git in the given working copy and returns its output.
+fn git(repository: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repository)
+        .args(args)
+        .output()
+        .chain_err(|| "Unable to run git")?;
+
+    if !output.status.success() {
+        return Err(ErrorKind::GitError(
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )
+        .into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn write_user_data(app: &App, repository: &Path) -> Result<()> {
+    let path = repository.join(USER_DATA_FILE);
+    let json = app.user_data().to_json()?;
+
+    File::create(&path)
+        .and_then(|mut file| file.write_all(json.as_bytes()))
+        .chain_err(|| format!("Unable to write user data to {:?}", path))?;
+
+    git(repository, &["add", USER_DATA_FILE])?;
+
+    Ok(())
+}
+
+/// Commits the user data, if it has changed.
+fn commit(repository: &Path, message: &str) -> Result<()> {
+    if git(repository, &["diff", "--cached", "--quiet"]).is_err() {
+        git(repository, &["commit", "--quiet", "-m", message])?;
+    }
+
+    Ok(())
+}
+
+/// Loads the user data of the given commit, if it contains user data.
+fn load_user_data(repository: &Path, commit: &str) -> Result<Option<UserData>> {
+    match git(
+        repository,
+        &["show", &format!("{}:{}", commit, USER_DATA_FILE)],
+    ) {
+        Ok(json) => UserData::from_json(repository.to_path_buf(), &json)
+            .map(Some)
+            .chain_err(|| format!("Unable to load user data from {}", commit)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Merges the user data of the upstream branch and commits the merge started by `sync_git`.
+fn merge_upstream(
+    app: &mut App,
+    repository: &Path,
+    upstream: &str,
+    base: Option<&UserData>,
+) -> Result<()> {
+    if let Some(other) = load_user_data(repository, upstream)? {
+        app.merge_user_data(other, upstream, base)?;
+    }
+
+    write_user_data(app, repository)?;
+    git(
+        repository,
+        &[
+            "commit",
+            "--quiet",
+            "-m",
+            &format!("Merge user data from {}", upstream),
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Synchronizes the user data through a git working copy.
+///
+/// Commits the local user data into the working copy, then fetches the upstream branch. If
+/// it has changes that aren't part of the local history yet, they are merged with the same
+/// logic as `bingers merge` (instead of a textual merge), using the user data of the common
+/// ancestor as base, and the result is pushed.
+pub fn sync_git(app: &mut App, repository: &Path) -> Result<()> {
+    git(repository, &["rev-parse", "--git-dir"])
+        .chain_err(|| format!("{:?} is not a git working copy", repository))?;
+
+    write_user_data(app, repository)?;
+    commit(repository, "Update user data")?;
+
+    let upstream = match git(
+        repository,
+        &[
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ],
+    ) {
+        Ok(upstream) => upstream,
+        Err(_) => {
+            println!("Committed user data, no upstream branch configured to sync with.");
+            return Ok(());
+        }
+    };
+
+    git(repository, &["fetch", "--quiet"])?;
+
+    if git(
+        repository,
+        &["merge-base", "--is-ancestor", &upstream, "HEAD"],
+    )
+    .is_err()
+    {
+        // The user data of the common ancestor is the base of a three-way merge
+        let base = match git(repository, &["merge-base", "HEAD", &upstream]) {
+            Ok(commit) => load_user_data(repository, &commit)?,
+            Err(_) => None,
+        };
+
+        // Record the merge, but keep the local tree. The user data is merged below.
+        git(
+            repository,
+            &[
+                "merge",
+                "--quiet",
+                "--no-commit",
+                "--no-ff",
+                "-s",
+                "ours",
+                &upstream,
+            ],
+        )?;
+
+        if let Err(e) = merge_upstream(app, repository, &upstream, base.as_ref()) {
+            // Leave the working copy as it was, so that the next sync can start over
+            let _ = git(repository, &["merge", "--abort"]);
+            return Err(e);
+        }
+    }
+
+    git(repository, &["push", "--quiet"])?;
+    println!("Synchronized user data with {}.", upstream);
+
+    Ok(())
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    File::create(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .chain_err(|| format!("Unable to write {:?}", path))
+}
+
+/// Synchronizes the user data with a file in a WebDAV folder (e.g. on Nextcloud).
+///
+/// The remote file is only replaced if it hasn't changed since it has been downloaded (using
+/// its ETag). If it has changed since the last sync, it is merged first. The user data as of
+/// the last sync serves as the common base, so that only conflicting changes need to be
+/// resolved interactively.
+pub fn sync_webdav(app: &mut App, config: &WebDavConfig) -> Result<()> {
+    sync_webdav_in(app, config, &user_data::data_dir()?)
+}
+
+/// Synchronizes like `sync_webdav()`, keeping the state of the last sync in `data_dir`.
+fn sync_webdav_in(app: &mut App, config: &WebDavConfig, data_dir: &Path) -> Result<()> {
+    let mut webdav = WebDav::new(config)?;
+    let source = webdav.uri().to_string();
+
+    let base_path = data_dir.join(WEBDAV_BASE_FILE);
+    let etag_path = data_dir.join(WEBDAV_ETAG_FILE);
+
+    let base = match UserData::load_file(&base_path) {
+        Ok(base) => Some(base),
+        Err(Error(ErrorKind::IoError(ref e), _)) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let synced_etag = match fs::read_to_string(&etag_path) {
+        Ok(etag) => Some(etag.trim().to_string()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(Error::from(e)).chain_err(|| format!("Unable to read {:?}", etag_path))
+        }
+    };
+
+    for _ in 0..WEBDAV_ATTEMPTS {
+        let remote = webdav.get()?;
+        if let Some((ref json, ref etag)) = remote {
+            if etag.is_none() {
+                bail!("{} doesn't support ETags", source);
+            }
+
+            if *etag != synced_etag {
+                let other = UserData::from_json(data_dir.to_path_buf(), json)
+                    .chain_err(|| format!("Unable to load user data from {}", source))?;
+                app.merge_user_data(other, &source, base.as_ref())?;
+            }
+        }
+
+        let json = app.user_data().to_json()?;
+        let (etag, message) = match remote {
+            Some((ref remote_json, ref etag)) if *remote_json == json => {
+                (etag.clone(), "User data is up to date with")
+            }
+            _ => match webdav.put(&json, remote.and_then(|(_, etag)| etag).as_deref())? {
+                Upload::Stored(etag) => (etag, "Synchronized user data with"),
+                // Changed by someone else since it has been downloaded, so merge again
+                Upload::Conflict => continue,
+            },
+        };
+
+        write_file(&base_path, &json)?;
+        match etag {
+            Some(etag) => write_file(&etag_path, &etag)?,
+            None => fs::remove_file(&etag_path)
+                .or_else(|e| match e.kind() {
+                    io::ErrorKind::NotFound => Ok(()),
+                    _ => Err(e),
+                })
+                .chain_err(|| format!("Unable to remove {:?}", etag_path))?,
+        }
+
+        println!("{} {}.", message, source);
+        return Ok(());
+    }
+
+    bail!(
+        "{} keeps changing while synchronizing, please try again later",
+        source
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::path::PathBuf;
+
+    use user_data::tests::show_json;
+    use webdav::tests::start_webdav_stand_in;
+
+    /// Empty data directory of a machine.
+    fn data_dir(name: &str) -> PathBuf {
+        let data_dir = env::temp_dir().join("bingers_webdav_sync_test").join(name);
+        let _ = fs::remove_dir_all(&data_dir);
+        fs::create_dir_all(&data_dir).unwrap();
+
+        data_dir
+    }
+
+    /// App with the given shows (id, name, viewing state, tags) as user data. Shows have to be
+    /// ordered by id, like in stored user data.
+    fn app(data_dir: &Path, shows: &[(usize, &str, &str, &str)]) -> App {
+        let shows: Vec<String> = shows
+            .iter()
+            .map(|&(id, name, viewing_state, tags)| {
+                show_json(
+                    id,
+                    name,
+                    &format!(r#""viewingState": "{}", "tags": [{}]"#, viewing_state, tags),
+                )
+            })
+            .collect();
+        let json = format!(
+            r#"{{"version": 1, "subscribed_shows": [{}], "unwatched_episodes": []}}"#,
+            shows.join(", ")
+        );
+
+        App::with_user_data(UserData::from_json(data_dir.to_path_buf(), &json).unwrap()).unwrap()
+    }
+
+    fn shows(json: &str) -> Vec<(String, String, Vec<String>)> {
+        UserData::from_json(PathBuf::new(), json)
+            .unwrap()
+            .subscribed_shows()
+            .iter()
+            .map(|show| {
+                (
+                    show.name.clone(),
+                    show.viewing_state.to_string().to_lowercase(),
+                    show.tags.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sync_two_machines_through_webdav() {
+        let (addr, file, interference) = start_webdav_stand_in();
+        let config = WebDavConfig {
+            url: format!("http://{}/bingers/user_data.json", addr),
+            username: None,
+            password: None,
+        };
+        let remote = || file.lock().unwrap().clone().unwrap();
+        let orville = (20263, "The Orville", "watching", "");
+        let discovery = (7480, "Star Trek: Discovery", "watching", "");
+
+        // First machine uploads, second one is up to date with it
+        let (a_dir, b_dir) = (data_dir("a"), data_dir("b"));
+        let mut a = app(&a_dir, &[orville]);
+        sync_webdav_in(&mut a, &config, &a_dir).unwrap();
+        assert_eq!(1, remote().1);
+        let mut b = app(&b_dir, &[orville]);
+        sync_webdav_in(&mut b, &config, &b_dir).unwrap();
+        assert_eq!(1, remote().1);
+        assert_eq!(
+            "\"1\"",
+            fs::read_to_string(b_dir.join(WEBDAV_ETAG_FILE)).unwrap()
+        );
+
+        // Second machine subscribes to a show, the remote file hasn't changed in the meantime
+        let mut b = app(&b_dir, &[discovery, orville]);
+        sync_webdav_in(&mut b, &config, &b_dir).unwrap();
+        assert_eq!(2, remote().1);
+
+        // First machine pauses a show. Merged with the base, the show of the second machine
+        // is added without asking. Someone else uploads while syncing, which is merged, too.
+        let mut a = app(&a_dir, &[(20263, "The Orville", "paused", "")]);
+        let c = app(
+            &data_dir("c"),
+            &[
+                (7480, "Star Trek: Discovery", "watching", r#""scifi""#),
+                orville,
+            ],
+        );
+        *interference.lock().unwrap() = Some(c.user_data().to_json().unwrap());
+        sync_webdav_in(&mut a, &config, &a_dir).unwrap();
+        let (json, version) = remote();
+        assert_eq!(4, version);
+        assert_eq!(
+            vec![
+                (
+                    "Star Trek: Discovery".to_string(),
+                    "watching".to_string(),
+                    vec!["scifi".to_string()]
+                ),
+                ("The Orville".to_string(), "paused".to_string(), Vec::new()),
+            ],
+            shows(&json)
+        );
+        assert_eq!(
+            json,
+            fs::read_to_string(a_dir.join(WEBDAV_BASE_FILE)).unwrap()
+        );
+        assert_eq!(
+            "\"4\"",
+            fs::read_to_string(a_dir.join(WEBDAV_ETAG_FILE)).unwrap()
+        );
+
+        // Second machine takes over the changes, without uploading anything
+        sync_webdav_in(&mut b, &config, &b_dir).unwrap();
+        assert_eq!(4, remote().1);
+        assert_eq!(json, b.user_data().to_json().unwrap());
+    }
+}
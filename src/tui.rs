@@ -0,0 +1,541 @@
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+use termion::{self, clear, cursor, style};
+
+use app::App;
+use errors::*;
+use tvmaze_api::{Episode, Show, TvMazeApi};
+
+const HELP: &str = "q: quit  tab: switch pane  j/k: move  w: watched  u: unwatched  r: refresh";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+    Shows,
+    Episodes,
+    Queue,
+}
+
+type UpdateResult = Result<(Vec<Show>, Vec<Episode>)>;
+
+/// Selected row and scroll offset of a list.
+#[derive(Default)]
+struct ListState {
+    selected: usize,
+    offset: usize,
+}
+
+impl ListState {
+    fn clamp(&mut self, len: usize) {
+        if self.selected >= len {
+            self.selected = len.saturating_sub(1);
+        }
+    }
+
+    fn move_by(&mut self, delta: isize, len: usize) {
+        let selected = self.selected as isize + delta;
+        self.selected = if selected < 0 { 0 } else { selected as usize };
+        self.clamp(len);
+    }
+
+    /// Adjusts the scroll offset such that the selected row is visible.
+    fn scroll(&mut self, height: usize) {
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if height > 0 && self.selected >= self.offset + height {
+            self.offset = self.selected + 1 - height;
+        }
+    }
+}
+
+struct Tui {
+    pane: Pane,
+    shows: ListState,
+    episodes: ListState,
+    queue: ListState,
+    status: String,
+    refresh: Option<Receiver<UpdateResult>>,
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    let mut truncated: String = text.chars().take(width).collect();
+    let length = truncated.chars().count();
+    truncated.extend((length..width).map(|_| ' '));
+    truncated
+}
+
+fn episode_label(episode: &Episode) -> String {
+    let air_date = match episode.airstamp {
+        Some(airstamp) => format!("{}", airstamp.format("%b %d, %Y")),
+        None => "TBD".to_string(),
+    };
+
+    format!(
+        "{} S{:02}E{:02} {} ({})",
+        if episode.watched { "✓" } else { " " },
+        episode.season,
+        episode.number,
+        episode.name,
+        air_date
+    )
+}
+
+impl Tui {
+    fn new() -> Self {
+        Self {
+            pane: Pane::Shows,
+            shows: ListState::default(),
+            episodes: ListState::default(),
+            queue: ListState::default(),
+            status: HELP.to_string(),
+            refresh: None,
+        }
+    }
+
+    fn selected_show<'a>(&self, app: &'a App) -> Option<&'a Show> {
+        app.user_data()
+            .subscribed_shows_by_most_recent()
+            .get(self.shows.selected)
+            .cloned()
+    }
+
+    /// Unwatched episodes of the selected show (including watched episodes after a gap).
+    fn show_episodes<'a>(&self, app: &'a App) -> Vec<&'a Episode> {
+        match self.selected_show(app) {
+            Some(show) => app
+                .user_data()
+                .unwatched_episodes()
+                .iter()
+                .filter(|episode| episode.show_id == show.id)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn queue<'a>(&self, app: &'a App) -> Vec<&'a Episode> {
+        app.user_data()
+            .unwatched_episodes_oldest_first()
+            .into_iter()
+            .filter(|episode| !episode.watched)
+            .collect()
+    }
+
+    fn draw<W: Write>(&mut self, screen: &mut W, app: &App) -> Result<()> {
+        let (width, height) = termion::terminal_size()?;
+        let (width, height) = (width as usize, height as usize);
+        let list_height = height.saturating_sub(4);
+
+        let shows = app.user_data().subscribed_shows_by_most_recent();
+        let unwatched = self.queue(app);
+        let show_rows: Vec<String> = shows
+            .iter()
+            .map(|show| {
+                let count = unwatched
+                    .iter()
+                    .filter(|episode| episode.show_id == show.id)
+                    .count();
+                if count > 0 {
+                    format!("{} ({})", show.name, count)
+                } else {
+                    show.name.clone()
+                }
+            })
+            .collect();
+        self.shows.clamp(show_rows.len());
+
+        let episode_rows: Vec<String> = self
+            .show_episodes(app)
+            .iter()
+            .map(|episode| episode_label(episode))
+            .collect();
+        self.episodes.clamp(episode_rows.len());
+
+        let queue_rows: Vec<String> = unwatched
+            .iter()
+            .map(|episode| {
+                let show_name = shows
+                    .iter()
+                    .find(|show| show.id == episode.show_id)
+                    .map(|show| show.name.as_str())
+                    .unwrap_or("???");
+                format!("{}: {}", show_name, episode_label(episode))
+            })
+            .collect();
+        self.queue.clamp(queue_rows.len());
+
+        let last_watched = match self.selected_show(app) {
+            Some(show) if show.last_watched_episode != (0, 0) => format!(
+                "Episodes (last watched S{:02}E{:02})",
+                show.last_watched_episode.0, show.last_watched_episode.1
+            ),
+            _ => "Episodes".to_string(),
+        };
+
+        write!(screen, "{}", clear::All)?;
+
+        let shows_width = width * 3 / 10;
+        let episodes_width = width * 35 / 100;
+        let queue_width = width.saturating_sub(shows_width + episodes_width);
+
+        let panes = [
+            (
+                Pane::Shows,
+                "Shows",
+                &show_rows,
+                &mut self.shows,
+                0,
+                shows_width,
+            ),
+            (
+                Pane::Episodes,
+                last_watched.as_str(),
+                &episode_rows,
+                &mut self.episodes,
+                shows_width,
+                episodes_width,
+            ),
+            (
+                Pane::Queue,
+                "Unwatched",
+                &queue_rows,
+                &mut self.queue,
+                shows_width + episodes_width,
+                queue_width,
+            ),
+        ];
+
+        for (pane, title, rows, state, x, pane_width) in panes {
+            let inner_width = pane_width.saturating_sub(1);
+            let focused = pane == self.pane;
+
+            write!(screen, "{}", cursor::Goto(x as u16 + 1, 1))?;
+            if focused {
+                write!(screen, "{}", style::Bold)?;
+            }
+            write!(screen, "{}{}", truncate(title, inner_width), style::Reset)?;
+            write!(
+                screen,
+                "{}{}",
+                cursor::Goto(x as u16 + 1, 2),
+                truncate(&"-".repeat(inner_width), inner_width)
+            )?;
+
+            state.scroll(list_height);
+            for (i, row) in rows.iter().enumerate().skip(state.offset).take(list_height) {
+                write!(
+                    screen,
+                    "{}",
+                    cursor::Goto(x as u16 + 1, (i - state.offset) as u16 + 3)
+                )?;
+                if focused && i == state.selected {
+                    write!(screen, "{}", style::Invert)?;
+                }
+                write!(screen, "{}{}", truncate(row, inner_width), style::Reset)?;
+            }
+        }
+
+        write!(
+            screen,
+            "{}{}",
+            cursor::Goto(1, height as u16),
+            truncate(&self.status, width)
+        )?;
+        screen.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns show id, season and episode number of the selected row.
+    fn selection(&self, app: &App) -> Option<(usize, Option<usize>, Option<usize>)> {
+        match self.pane {
+            Pane::Shows => self.selected_show(app).map(|show| (show.id, None, None)),
+            Pane::Episodes => self
+                .show_episodes(app)
+                .get(self.episodes.selected)
+                .map(|episode| (episode.show_id, Some(episode.season), Some(episode.number))),
+            Pane::Queue => self
+                .queue(app)
+                .get(self.queue.selected)
+                .map(|episode| (episode.show_id, Some(episode.season), Some(episode.number))),
+        }
+    }
+
+    fn mark(&mut self, app: &mut App, watched: bool) {
+        let (show_id, season, episode) = match self.selection(app) {
+            Some(selection) => selection,
+            None => return,
+        };
+
+        let result = if watched {
+            app.mark_as_watched_by_id(show_id, season, episode)
+        } else {
+            app.mark_as_unwatched_by_id(show_id, season, episode)
+        };
+
+        let show_name = app
+            .user_data()
+            .subscribed_shows()
+            .iter()
+            .find(|show| show.id == show_id)
+            .map(|show| show.name.clone())
+            .unwrap_or_default();
+        let state = if watched { "watched" } else { "unwatched" };
+
+        self.status = match result {
+            Ok(Some((season, episode))) => format!(
+                "Marked season {} episode {} of {} as {}.",
+                season, episode, show_name, state
+            ),
+            Ok(None) => format!("Nothing to mark as {}.", state),
+            Err(e) => format!("Error: {}", e),
+        };
+    }
+
+    /// Starts fetching updates on a background thread.
+    fn start_refresh(&mut self, app: &App) {
+        if self.refresh.is_some() {
+            return;
+        }
+
+        let shows = app.subscribed_shows_last_updated(None);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let result = TvMazeApi::new(false)
+                .and_then(|mut api| App::fetch_updates(&mut api, &shows, false, false));
+            let _ = sender.send(result);
+        });
+
+        self.refresh = Some(receiver);
+        self.status = "Updating...".to_string();
+    }
+
+    /// Applies fetched updates, if they are available. Returns true if the screen changed.
+    fn poll_refresh(&mut self, app: &mut App) -> bool {
+        let result = match self.refresh {
+            Some(ref receiver) => match receiver.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => Err("Update thread died".into()),
+            },
+            None => return false,
+        };
+        self.refresh = None;
+
+        // Changes of shows and episodes are shown in the status line, as printing them would
+        // garble the screen
+        self.status = match result.and_then(|(shows, episodes)| app.apply_updates(shows, episodes))
+        {
+            Ok((new_episodes, ref changes)) if changes.is_empty() => {
+                format!("Update finished, {} new episode(s).", new_episodes)
+            }
+            Ok((new_episodes, changes)) => format!(
+                "Update finished, {} new episode(s). {}",
+                new_episodes,
+                changes.join("; ")
+            ),
+            Err(e) => format!("Update failed: {}", e),
+        };
+
+        true
+    }
+
+    fn move_selection(&mut self, delta: isize, app: &App) {
+        match self.pane {
+            Pane::Shows => {
+                let len = app.user_data().subscribed_shows().len();
+                self.shows.move_by(delta, len);
+                self.episodes = ListState::default();
+            }
+            Pane::Episodes => {
+                let len = self.show_episodes(app).len();
+                self.episodes.move_by(delta, len);
+            }
+            Pane::Queue => {
+                let len = self.queue(app).len();
+                self.queue.move_by(delta, len);
+            }
+        }
+    }
+
+    fn switch_pane(&mut self, forward: bool) {
+        self.pane = match (self.pane, forward) {
+            (Pane::Shows, true) | (Pane::Queue, false) => Pane::Episodes,
+            (Pane::Episodes, true) | (Pane::Shows, false) => Pane::Queue,
+            (Pane::Queue, true) | (Pane::Episodes, false) => Pane::Shows,
+        };
+    }
+}
+
+/// Runs the interactive terminal user interface until the user quits.
+pub fn run(app: &mut App) -> Result<()> {
+    // Output of the web API and of the update would garble the screen
+    app.set_verbose(false);
+
+    let stdout = io::stdout().into_raw_mode()?;
+    let mut screen = AlternateScreen::from(stdout);
+    write!(screen, "{}", cursor::Hide)?;
+
+    let mut keys = termion::async_stdin().keys();
+    let mut tui = Tui::new();
+    let mut redraw = true;
+
+    loop {
+        if tui.poll_refresh(app) {
+            redraw = true;
+        }
+
+        if redraw {
+            tui.draw(&mut screen, app)?;
+            redraw = false;
+        }
+
+        let key = match keys.next() {
+            Some(key) => key?,
+            None => {
+                thread::sleep(Duration::from_millis(50));
+                continue;
+            }
+        };
+        redraw = true;
+
+        match key {
+            Key::Char('q') | Key::Esc | Key::Ctrl('c') => break,
+            Key::Char('\t') | Key::Right | Key::Char('l') => tui.switch_pane(true),
+            Key::BackTab | Key::Left | Key::Char('h') => tui.switch_pane(false),
+            Key::Char('\n') if tui.pane == Pane::Shows => tui.pane = Pane::Episodes,
+            Key::Down | Key::Char('j') => tui.move_selection(1, app),
+            Key::Up | Key::Char('k') => tui.move_selection(-1, app),
+            Key::PageDown => tui.move_selection(10, app),
+            Key::PageUp => tui.move_selection(-10, app),
+            Key::Char('w') => tui.mark(app, true),
+            Key::Char('u') => {
+                tui.status = "Loading episodes...".to_string();
+                tui.draw(&mut screen, app)?;
+                tui.mark(app, false);
+            }
+            Key::Char('r') => tui.start_refresh(app),
+            _ => redraw = false,
+        }
+    }
+
+    write!(screen, "{}", cursor::Show)?;
+    screen.flush()?;
+
+    // The app might be used further, e.g. when started from the shell
+    app.set_verbose(true);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use user_data::tests::show_json;
+    use user_data::UserData;
+
+    /// App with The Orville (two unwatched episodes) and Star Trek: Discovery (one).
+    fn app() -> App {
+        let json = format!(
+            r#"{{"version": 1,
+                "subscribed_shows": [{}, {}],
+                "unwatched_episodes": [
+                    {{"id": 1172410, "show_id": 20263, "name": "Old Wounds",
+                     "season": 1, "number": 1, "airstamp": "2017-09-11T00:00:00Z",
+                     "runtime": 60, "watched": false}},
+                    {{"id": 1201556, "show_id": 20263, "name": "Command Performance",
+                     "season": 1, "number": 2, "airstamp": "2017-09-18T00:00:00Z",
+                     "runtime": 60, "watched": false}},
+                    {{"id": 892064, "show_id": 7480, "name": "The Vulcan Hello",
+                     "season": 1, "number": 1, "airstamp": "2017-09-25T00:30:00Z",
+                     "runtime": 60, "watched": false}}]}}"#,
+            show_json(20263, "The Orville", r#""updated": 2"#),
+            show_json(7480, "Star Trek: Discovery", r#""updated": 1"#)
+        );
+        let user_data =
+            UserData::from_json(env::temp_dir().join("bingers_tui_test"), &json).unwrap();
+
+        App::with_user_data(user_data).unwrap()
+    }
+
+    #[test]
+    fn move_and_scroll_list() {
+        let mut state = ListState::default();
+        state.move_by(-1, 5);
+        assert_eq!(0, state.selected);
+        state.move_by(3, 5);
+        assert_eq!(3, state.selected);
+        state.move_by(10, 5);
+        assert_eq!(4, state.selected);
+
+        // Selected row stays visible
+        state.scroll(2);
+        assert_eq!(3, state.offset);
+        state.move_by(-4, 5);
+        state.scroll(2);
+        assert_eq!(0, state.offset);
+
+        // List got shorter, e.g. after marking episodes as watched
+        state.selected = 4;
+        state.clamp(2);
+        assert_eq!(1, state.selected);
+        state.clamp(0);
+        assert_eq!(0, state.selected);
+    }
+
+    #[test]
+    fn switch_panes() {
+        let mut tui = Tui::new();
+        tui.switch_pane(true);
+        assert!(tui.pane == Pane::Episodes);
+        tui.switch_pane(true);
+        assert!(tui.pane == Pane::Queue);
+        tui.switch_pane(true);
+        assert!(tui.pane == Pane::Shows);
+        tui.switch_pane(false);
+        assert!(tui.pane == Pane::Queue);
+    }
+
+    #[test]
+    fn select_and_mark_episodes() {
+        let mut app = app();
+        let mut tui = Tui::new();
+
+        // Shows are ordered by last update, selecting another show resets the episode list
+        assert_eq!(Some((20263, None, None)), tui.selection(&app));
+        tui.switch_pane(true);
+        tui.move_selection(1, &app);
+        assert_eq!(Some((20263, Some(1), Some(2))), tui.selection(&app));
+        tui.switch_pane(false);
+        tui.move_selection(1, &app);
+        assert_eq!(Some((7480, None, None)), tui.selection(&app));
+        assert_eq!(0, tui.episodes.selected);
+
+        // Queue lists the episodes of all shows, oldest first
+        tui.pane = Pane::Queue;
+        tui.move_selection(10, &app);
+        assert_eq!(Some((7480, Some(1), Some(1))), tui.selection(&app));
+
+        tui.pane = Pane::Shows;
+        tui.move_selection(-1, &app);
+        tui.mark(&mut app, true);
+        assert_eq!(
+            "Marked season 1 episode 1 of The Orville as watched.",
+            tui.status
+        );
+        assert_eq!(2, tui.queue(&app).len());
+
+        tui.pane = Pane::Queue;
+        tui.move_selection(-10, &app);
+        assert_eq!(Some((20263, Some(1), Some(2))), tui.selection(&app));
+    }
+}
@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::str::FromStr;
 
@@ -25,6 +26,14 @@ pub struct Network {
     pub name: String,
 }
 
+/// Ids of a show on other services.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Externals {
+    pub tvrage: Option<usize>,
+    pub thetvdb: Option<usize>,
+    pub imdb: Option<String>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum Day {
     Monday,
@@ -44,6 +53,8 @@ impl fmt::Display for Day {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Schedule {
+    #[serde(default)]
+    pub time: String,
     pub days: Vec<Day>,
 }
 
@@ -67,6 +78,38 @@ impl fmt::Display for Status {
     }
 }
 
+/// Viewing state of a subscribed show, as assigned by the user.
+///
+/// Unlike `Status`, which describes the production of a show, this describes whether
+/// we are still watching it.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ViewingState {
+    #[default]
+    Watching,
+    Paused,
+    Dropped(Option<String>),
+    Completed,
+    PlanToWatch,
+}
+
+impl ViewingState {
+    /// Paused and dropped shows are neither listed nor updated.
+    pub fn is_inactive(&self) -> bool {
+        matches!(*self, ViewingState::Paused | ViewingState::Dropped(_))
+    }
+}
+
+impl fmt::Display for ViewingState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ViewingState::Dropped(_) => write!(f, "Dropped"),
+            ViewingState::PlanToWatch => write!(f, "Plan to watch"),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Show {
@@ -77,11 +120,33 @@ pub struct Show {
     pub web_channel: Option<Network>,
     pub status: Status,
     pub runtime: Option<usize>,
+    #[serde(default)]
+    pub premiered: Option<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub externals: Externals,
     pub schedule: Schedule,
     #[serde(rename = "updated", default)]
     pub last_updated: u64,
     #[serde(default)]
     pub last_watched_episode: (usize, usize),
+    #[serde(default)]
+    pub viewing_state: ViewingState,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: Vec<String>,
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Shows with higher priority are planned first
+    #[serde(default)]
+    pub priority: i32,
+    /// Total number of episodes per season (including episodes that haven't aired yet)
+    #[serde(default)]
+    pub episode_counts: BTreeMap<usize, usize>,
 }
 
 impl Ord for Show {
@@ -139,6 +204,74 @@ impl fmt::Display for Show {
 }
 
 impl Show {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(&tag.to_lowercase())
+    }
+
+    /// Returns true if no tag is given or if the show has the given tag.
+    pub fn matches_tag(&self, tag: Option<&str>) -> bool {
+        match tag {
+            Some(tag) => self.has_tag(tag),
+            None => true,
+        }
+    }
+
+    /// Returns the user-provided data of the show that contains the query (ignoring case).
+    pub fn search_user_data(&self, query: &str) -> Vec<String> {
+        let query = query.to_lowercase();
+        let contains_query = |text: &str| text.to_lowercase().contains(&query);
+
+        let mut matches = Vec::new();
+
+        if contains_query(&self.name) {
+            matches.push(format!("Name: {}", self.name));
+        }
+
+        for tag in self.tags.iter().filter(|tag| contains_query(tag)) {
+            matches.push(format!("Tag: {}", tag));
+        }
+
+        for (key, value) in self
+            .fields
+            .iter()
+            .filter(|&(key, value)| contains_query(key) || contains_query(value))
+        {
+            matches.push(format!("{}: {}", key, value));
+        }
+
+        for note in self.notes.iter().filter(|note| contains_query(note)) {
+            matches.push(format!("Note: {}", note));
+        }
+
+        matches
+    }
+
+    /// Summary without HTML tags and with the most common entities decoded.
+    pub fn plain_summary(&self) -> Option<String> {
+        let summary = self.summary.as_ref()?;
+
+        let mut plain = String::new();
+        let mut in_tag = false;
+        for c in summary.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                _ if !in_tag => plain.push(c),
+                _ => {}
+            }
+        }
+
+        let plain = plain
+            .replace("&nbsp;", " ")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&");
+
+        Some(plain.trim().to_string())
+    }
+
     pub fn network_name(&self) -> &str {
         match self.network {
             Some(ref network) => &network.name,
@@ -156,7 +289,7 @@ pub struct SearchResult {
     pub show: Show,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Episode {
     #[serde(rename = "id")]
     pub episode_id: usize,
@@ -226,6 +359,10 @@ impl TvMazeApi {
         })
     }
 
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
     /// Create a new GET request.
     fn create_get_request(
         &self,
@@ -308,6 +445,67 @@ impl TvMazeApi {
             .chain_err(|| "HTTP request failed")
     }
 
+    /// Looks up the TvMaze.com id of a show by its id on another service (`tvrage`, `thetvdb`
+    /// or `imdb`). Returns `None` if no show is found.
+    pub fn lookup_show(&mut self, service: &str, id: &str) -> Result<Option<usize>> {
+        // Construct URI
+        let uri = &format!(
+            "https://api.tvmaze.com/lookup/shows?{}={}",
+            service,
+            utf8_percent_encode(id, QUERY_ENCODE_SET)
+        );
+        let uri = Uri::from_str(uri).chain_err(|| format!("Invalid URI [{}]", uri))?;
+
+        let retry_strategy = FibonacciBackoff::from_millis(1000).take(6);
+        let client = &self.client;
+        let verbose = self.verbose;
+
+        // The lookup redirects to the show, e.g. to https://api.tvmaze.com/shows/20263
+        let show_id = RetryIf::spawn(
+            retry_strategy,
+            move || {
+                let uri = uri.clone();
+
+                if verbose {
+                    println!("GET {}", uri);
+                }
+
+                client
+                    .get(uri.clone())
+                    .map_err(std::convert::Into::into)
+                    .and_then(move |res| {
+                        if verbose {
+                            println!("{} {}", res.status(), uri);
+                        }
+
+                        match res.status() {
+                            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => res
+                                .headers()
+                                .get(hyper::header::LOCATION)
+                                .and_then(|location| location.to_str().ok())
+                                .and_then(|location| location.rsplit('/').next())
+                                .and_then(|id| id.parse::<usize>().ok())
+                                .map(Some)
+                                .ok_or_else(|| "Invalid redirect of show lookup".into()),
+                            StatusCode::NOT_FOUND => Ok(None),
+                            status => Err(ErrorKind::HttpError(status, uri).into()),
+                        }
+                    })
+            },
+            |e: &::errors::Error| match *e {
+                Error(ErrorKind::HttpError(status, _), _) => {
+                    status == StatusCode::TOO_MANY_REQUESTS
+                }
+                _ => false,
+            },
+        );
+
+        self.core
+            .borrow_mut()
+            .run(show_id.map_err(Error::from))
+            .chain_err(|| "HTTP request failed")
+    }
+
     #[allow(dead_code)]
     pub fn get_shows(&mut self, ids: &[usize]) -> Result<Vec<Show>> {
         let mut requests = FuturesUnordered::new();
@@ -455,3 +653,27 @@ impl TvMazeApi {
         Ok(episodes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use user_data::tests::show_json;
+
+    #[test]
+    fn strip_html_from_summary() {
+        let show: Show = ::serde_json::from_str(&show_json(
+            20263,
+            "The Orville",
+            r#""premiered": "2017-09-10", "genres": ["Comedy", "Adventure", "Science-Fiction"],
+                "summary": "<p><b>The Orville</b> is a comedic &amp; dramatic voyage.</p>""#,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            Some("The Orville is a comedic & dramatic voyage.".to_string()),
+            show.plain_summary()
+        );
+        assert_eq!(3, show.genres.len());
+    }
+}
@@ -1,12 +1,14 @@
-use std::cmp::Ordering;
+use std::cmp::{max, min, Ordering};
+use std::collections::BTreeMap;
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use app_dirs::{get_data_root, AppDataType};
+use chrono::{DateTime, Utc};
 
 use errors::*;
-use tvmaze_api::{Episode, Show, Status};
+use tvmaze_api::{Episode, Show, Status, ViewingState};
 
 const VERSION: u32 = 1;
 
@@ -28,19 +30,109 @@ fn episode_is_less_than(episode: &Episode, episode_number: EpisodeNumber) -> boo
     }
 }
 
+/// Directory in which the user data is stored.
+pub fn data_dir() -> Result<PathBuf> {
+    let mut user_data_path = get_data_root(AppDataType::UserData)
+        .chain_err(|| "Unable to determine user data location.")?;
+    user_data_path.push("bingers");
+
+    Ok(user_data_path)
+}
+
+/// Counts the episodes of the given show per season.
+pub fn episode_counts(show_id: usize, episodes: &[Episode]) -> BTreeMap<usize, usize> {
+    let mut counts = BTreeMap::new();
+
+    for episode in episodes.iter().filter(|episode| episode.show_id == show_id) {
+        *counts.entry(episode.season).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+/// Number of watched, aired and total episodes of a season.
+#[derive(Debug, PartialEq)]
+pub struct SeasonProgress {
+    pub season: usize,
+    pub watched: usize,
+    pub aired: usize,
+    pub total: usize,
+}
+
 #[derive(Deserialize)]
 struct DetectVersion {
     version: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Change of the production status of a subscribed show, as detected during an update.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StatusChange {
+    pub show_id: usize,
+    pub previous: Status,
+    pub current: Status,
+    pub changed: DateTime<Utc>,
+}
+
+/// Difference between two user data files that can't be merged automatically.
+pub enum MergeConflict<'a> {
+    /// The show is only subscribed in the other user data (added there or removed here).
+    OnlyInOther(&'a Show),
+    /// The show is only subscribed here (added here or removed there).
+    OnlyHere(&'a Show),
+    /// The show (as subscribed here) has another viewing state in the other user data.
+    ViewingState(&'a Show, &'a ViewingState),
+}
+
+/// Entry of the watch history, optionally with a rating (1 - 10) and a note.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct WatchedEpisode {
+    pub episode_id: usize,
+    pub show_id: usize,
+    pub season: usize,
+    pub number: usize,
+    pub name: String,
+    pub runtime: Option<usize>,
+    pub watched: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl WatchedEpisode {
+    fn new(episode: &Episode, watched: DateTime<Utc>) -> Self {
+        Self {
+            episode_id: episode.episode_id,
+            show_id: episode.show_id,
+            season: episode.season,
+            number: episode.number,
+            name: episode.name.clone(),
+            runtime: episode.runtime,
+            watched,
+            rating: None,
+            note: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 struct UserDataV1 {
     version: u32,
     subscribed_shows: Vec<Show>,
     unwatched_episodes: Vec<Episode>,
+    #[serde(default)]
+    status_changes: Vec<StatusChange>,
+    #[serde(default)]
+    last_digest: Option<DateTime<Utc>>,
+    /// Unwatched episodes at the time of the last digest. `None` for user data of older
+    /// versions, which only compared air dates with `last_digest`.
+    #[serde(default)]
+    digested_episodes: Option<Vec<usize>>,
+    #[serde(default)]
+    history: Vec<WatchedEpisode>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct UserData {
     path: PathBuf,
     data: UserDataV1,
@@ -54,56 +146,75 @@ impl UserData {
                 version: 1,
                 subscribed_shows: Vec::new(),
                 unwatched_episodes: Vec::new(),
+                status_changes: Vec::new(),
+                last_digest: None,
+                digested_episodes: None,
+                history: Vec::new(),
             },
         }
     }
 
     pub fn load() -> Result<Self> {
-        let mut user_data_path = get_data_root(AppDataType::UserData)
-            .chain_err(|| "Unable to determine user data location.")?;
-        user_data_path.push("bingers");
+        let user_data_path = data_dir()?;
 
         let mut user_data_file = user_data_path.clone();
         user_data_file.push("user_data.json");
 
-        match File::open(&user_data_file) {
-            Ok(mut file) => {
-                // Read user data from file
-                let mut file_content = String::new();
-                file.read_to_string(&mut file_content)
-                    .chain_err(|| format!("Unable to read user data from {:?}", user_data_file))?;
-
-                // Detect version
-                let detect_version: DetectVersion = ::serde_json::from_str(&file_content)
-                    .chain_err(|| format!("Unable to parse version from {:?}", user_data_file))?;
-
-                if detect_version.version > VERSION {
-                    return Err(ErrorKind::UserDataVersionMismatch(
-                        VERSION,
-                        detect_version.version,
-                    )
-                    .into());
-                }
+        match UserData::load_file(&user_data_file) {
+            Ok(user_data) => Ok(user_data),
+            Err(Error(ErrorKind::IoError(ref e), _)) if e.kind() == io::ErrorKind::NotFound => {
+                // File doesn't exist yet, so create new user data
+                println!("No user data found, creating new.");
+                Ok(UserData::new(user_data_path))
+            }
+            Err(e) => Err(e),
+        }
+    }
 
-                // Deserialize
-                let mut user_data = UserData::new(user_data_path);
-                user_data.data = ::serde_json::from_str(&file_content).chain_err(|| {
-                    format!("Unable to deserialize user data from {:?}", user_data_file)
-                })?;
+    /// Loads user data from the given file, e.g. from another machine.
+    pub fn load_file(user_data_file: &Path) -> Result<Self> {
+        // Read user data from file
+        let mut file = File::open(user_data_file)?;
+        let mut file_content = String::new();
+        file.read_to_string(&mut file_content)
+            .chain_err(|| format!("Unable to read user data from {:?}", user_data_file))?;
+
+        let user_data_path = match user_data_file.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => PathBuf::new(),
+        };
 
-                Ok(user_data)
-            }
-            Err(e) => {
-                match e.kind() {
-                    // File doesn't exist yet, so create new user data
-                    io::ErrorKind::NotFound => {
-                        println!("No user data found, creating new.");
-                        Ok(UserData::new(user_data_path))
-                    }
-                    _ => Err(e.into()),
-                }
-            }
+        UserData::from_json(user_data_path, &file_content)
+            .chain_err(|| format!("Unable to load user data from {:?}", user_data_file))
+    }
+
+    /// Parses user data, which is stored in the given directory.
+    pub fn from_json(user_data_path: PathBuf, json: &str) -> Result<Self> {
+        // Detect version
+        let detect_version: DetectVersion =
+            ::serde_json::from_str(json).chain_err(|| "Unable to parse version")?;
+
+        if detect_version.version > VERSION {
+            return Err(ErrorKind::UserDataVersionMismatch(VERSION, detect_version.version).into());
         }
+
+        // Deserialize
+        let mut user_data = UserData::new(user_data_path);
+        user_data.data =
+            ::serde_json::from_str(json).chain_err(|| "Unable to deserialize user data")?;
+
+        Ok(user_data)
+    }
+
+    /// Serializes the user data as pretty-printed JSON. The order of shows, episodes and maps
+    /// is stable, so that changes can be diffed.
+    pub fn to_json(&self) -> Result<String> {
+        ::serde_json::to_string_pretty(&self.data).chain_err(|| "Unable to serialize user data.")
+    }
+
+    /// Directory in which the user data is stored.
+    pub fn directory(&self) -> &Path {
+        &self.path
     }
 
     pub fn store(&self) -> Result<()> {
@@ -125,8 +236,7 @@ impl UserData {
             .open(&user_data_tmp)
             .chain_err(|| format!("Unable to open {:?}", user_data_tmp))?;
 
-        let json =
-            ::serde_json::to_string(&self.data).chain_err(|| "Unable to serialize user data.")?;
+        let json = self.to_json()?;
 
         tmp_file
             .write_all(json.as_bytes())
@@ -192,925 +302,2480 @@ impl UserData {
         unwatched_episodes
     }
 
-    pub fn add_show(&mut self, show: Show) {
-        if !self.data.subscribed_shows.contains(&show) {
-            self.data.subscribed_shows.push(show);
-
-            self.data.subscribed_shows.sort();
-        }
-    }
+    /// The next unwatched episode of each show, together with the number of unwatched episodes
+    /// of that show. Episodes that have been watched after a gap are skipped.
+    pub fn next_episodes(&self) -> Vec<(&Episode, usize)> {
+        let mut next_episodes: BTreeMap<usize, (&Episode, usize)> = BTreeMap::new();
 
-    pub fn add_episodes(&mut self, episodes: Vec<Episode>) {
-        let mut episode_added = false;
-        for episode in episodes {
-            if !self.data.unwatched_episodes.contains(&episode) {
-                self.data.unwatched_episodes.push(episode);
-                episode_added = true;
+        for episode in self
+            .data
+            .unwatched_episodes
+            .iter()
+            .filter(|episode| !episode.watched)
+        {
+            let entry = next_episodes.entry(episode.show_id).or_insert((episode, 0));
+            if *episode < *entry.0 {
+                entry.0 = episode;
             }
+            entry.1 += 1;
         }
 
-        if episode_added {
-            self.data.unwatched_episodes.sort();
-        }
+        next_episodes.into_values().collect()
     }
 
-    pub fn remove_episodes(&mut self, show: &Show) {
+    /// Point in time when an episode of the given show was last marked as watched.
+    pub fn last_activity(&self, show_id: usize) -> Option<DateTime<Utc>> {
         self.data
-            .unwatched_episodes
-            .retain(|episode| episode.show_id != show.id);
+            .history
+            .iter()
+            .filter(|entry| entry.show_id == show_id)
+            .map(|entry| entry.watched)
+            .max()
     }
 
-    pub fn remove_show(&mut self, show: &Show) {
+    /// Status changes that have been detected since the given point in time.
+    pub fn status_changes_since(&self, since: Option<DateTime<Utc>>) -> Vec<&StatusChange> {
         self.data
-            .subscribed_shows
-            .retain(|subscribed_show| subscribed_show != show);
+            .status_changes
+            .iter()
+            .filter(|change| match since {
+                Some(since) => change.changed > since,
+                None => true,
+            })
+            .collect()
     }
 
-    /// Mark episode of given show as watched.
-    ///
-    /// If neither season nor episode are specified, will mark the next unwatched episode
-    /// as watched.
-    ///
-    /// If only season is specified, will mark the whole season as watched.
-    ///
-    /// If both season and episode are specified, will mark the exact episode as watched.
-    ///
-    /// Returns episode number of last episode that was marked as watched.
-    pub fn mark_as_watched(
-        &mut self,
-        show_id: usize,
-        season: Option<usize>,
-        episode: Option<usize>,
-    ) -> Option<(usize, usize)> {
-        // Mark episode(s) as watched
-        let last_marked = match (season, episode) {
-            (Some(season), None) => self.mark_season_as_watched(show_id, season),
-            (Some(season), Some(episode)) => self.mark_episode_as_watched(show_id, season, episode),
-            (None, None) => self.mark_next_episode_as_watched(show_id),
-            (None, Some(_)) => None,
-        };
-
-        if let Some(last_marked) = last_marked {
-            let mut gap = false;
-            let mut last_watched = (0, 0);
-            let mut show_index = None;
+    pub fn last_digest(&self) -> Option<DateTime<Utc>> {
+        self.data.last_digest
+    }
 
-            // Determine last watched episode (or rather the episode before the previously
-            // first unwatched episode)
-            for (i, show) in self
-                .data
-                .subscribed_shows
-                .iter()
-                .enumerate()
-                .filter(|&(_, show)| show.id == show_id)
-            {
-                last_watched = show.last_watched_episode;
-                show_index = Some(i);
-            }
+    /// Unwatched episodes that haven't been part of the user data at the time of the last
+    /// digest, oldest first.
+    ///
+    /// Episodes are new regardless of their air date, so that episodes that are added late
+    /// (e.g. backfilled on TVmaze) are still reported. Without a record of the last digest,
+    /// episodes that aired after `since` are new.
+    pub fn new_episodes_since_digest(&self, since: DateTime<Utc>) -> Vec<&Episode> {
+        self.unwatched_episodes_oldest_first()
+            .into_iter()
+            .filter(|episode| !episode.watched)
+            .filter(|episode| match self.data.digested_episodes {
+                Some(ref digested) => !digested.contains(&episode.episode_id),
+                None => match episode.airstamp {
+                    Some(airstamp) => airstamp > since,
+                    None => false,
+                },
+            })
+            .collect()
+    }
 
-            // Determine if there are unwatched episodes between the last watched episode
-            // and the episodes that were now marked as watched.
-            for _ in self
-                .data
+    /// Advances the digest cursor. Status changes that are covered by the digest are
+    /// discarded and the current unwatched episodes are remembered, so that they won't be
+    /// reported again.
+    pub fn set_last_digest(&mut self, last_digest: DateTime<Utc>) {
+        self.data
+            .status_changes
+            .retain(|change| change.changed > last_digest);
+        self.data.last_digest = Some(last_digest);
+        self.data.digested_episodes = Some(
+            self.data
                 .unwatched_episodes
                 .iter()
-                .filter(|episode| episode.show_id == show_id && !episode.watched)
-                .filter(|episode| episode_is_greater_than(episode, last_watched))
-                .filter(|episode| episode_is_less_than(episode, last_marked))
-            {
-                gap = true;
-            }
-
-            // Remove all watched episodes and update last watched episode.
-            //
-            // Don't remove watched episodes that are separated by the last_watched pointer with
-            // a gap of unwatched episodes.
-            //
-            // Cleans up watched episodes if gap is eliminated.
-            if !gap {
-                let mut last_watched = last_watched;
-                let mut stop = false;
-
-                // This is slightly dirty because it depends on the internal implementation
-                // of retain() (i.e. that the vector is iterated over in order from start to end).
-                // Tests should catch it, if that implementation ever should change...
-                self.data.unwatched_episodes.retain(|episode| {
-                    if episode.show_id == show_id && episode_is_greater_than(episode, last_watched)
-                    {
-                        // If the episode is marked as watched and we haven't yet hit a gap...
-                        if episode.watched && !stop {
-                            // ... update last_watched pointer and remove episode
-                            last_watched = (episode.season, episode.number);
-                            false
-                        } else {
-                            // We hit a gap. Retain all following episodes.
-                            stop = true;
-                            true
-                        }
-                    } else {
-                        // Keep episodes of other shows
-                        true
-                    }
-                });
-
-                if let Some(index) = show_index {
-                    self.data.subscribed_shows[index].last_watched_episode = last_watched;
-                }
-            }
-        }
+                .map(|episode| episode.episode_id)
+                .collect(),
+        );
+    }
 
-        last_marked
+    /// Watch history, in the order in which episodes have been marked as watched.
+    pub fn history(&self) -> &Vec<WatchedEpisode> {
+        &self.data.history
     }
 
-    // See clippy issue #3159 (https://github.com/rust-lang-nursery/rust-clippy/issues/3159)
-    #[cfg_attr(feature = "cargo-clippy", allow(clippy::never_loop))]
-    fn mark_next_episode_as_watched(&mut self, show_id: usize) -> Option<(usize, usize)> {
-        let mut marked = None;
+    /// Most recent rating of each rated episode of the given show, in episode order.
+    pub fn ratings(&self, show_id: usize) -> Vec<&WatchedEpisode> {
+        let mut ratings: Vec<&WatchedEpisode> = Vec::new();
 
-        for episode in self
+        for entry in self
             .data
-            .unwatched_episodes
-            .iter_mut()
-            .filter(|episode| episode.show_id == show_id && !episode.watched)
+            .history
+            .iter()
+            .filter(|entry| entry.show_id == show_id && entry.rating.is_some())
         {
-            episode.watched = true;
-            marked = Some((episode.season, episode.number));
-            break;
+            // Later entries replace earlier ratings of the same episode
+            ratings.retain(|rated| rated.episode_id != entry.episode_id);
+            ratings.push(entry);
         }
 
-        marked
+        ratings.sort_by_key(|entry| (entry.season, entry.number));
+        ratings
     }
 
-    fn mark_episode_as_watched(
+    /// Rates the most recent history entry of the given episode.
+    ///
+    /// Returns false if the episode isn't part of the watch history.
+    pub fn rate_episode(
         &mut self,
         show_id: usize,
         season: usize,
         number: usize,
-    ) -> Option<(usize, usize)> {
-        let mut marked = None;
-
-        for episode in self.data.unwatched_episodes.iter_mut().filter(|episode| {
-            episode.show_id == show_id
-                && episode.season == season
-                && episode.number == number
-                && !episode.watched
+        rating: Option<u8>,
+        note: Option<String>,
+    ) -> bool {
+        match self.data.history.iter_mut().rev().find(|entry| {
+            entry.show_id == show_id && entry.season == season && entry.number == number
         }) {
-            episode.watched = true;
-            marked = Some((episode.season, episode.number));
+            Some(entry) => {
+                if rating.is_some() {
+                    entry.rating = rating;
+                }
+                if note.is_some() {
+                    entry.note = note;
+                }
+                true
+            }
+            None => false,
         }
-
-        marked
     }
 
-    fn mark_season_as_watched(&mut self, show_id: usize, season: usize) -> Option<(usize, usize)> {
-        let mut marked = None;
-
-        for episode in self.data.unwatched_episodes.iter_mut().filter(|episode| {
-            episode.show_id == show_id && episode.season == season && !episode.watched
+    /// Sets when an episode has been watched, e.g. when it has been played on a media server.
+    /// Changes the most recent history entry of the episode, the history stays in time order.
+    ///
+    /// Returns false if the episode isn't in the watch history.
+    pub fn set_watched_time(
+        &mut self,
+        show_id: usize,
+        season: usize,
+        number: usize,
+        watched: DateTime<Utc>,
+    ) -> bool {
+        match self.data.history.iter_mut().rev().find(|entry| {
+            entry.show_id == show_id && entry.season == season && entry.number == number
         }) {
-            episode.watched = true;
-            marked = Some((episode.season, episode.number));
+            Some(entry) => entry.watched = watched,
+            None => return false,
         }
+        self.data.history.sort_by_key(|entry| entry.watched);
 
-        marked
+        true
     }
 
-    /// Updates the metadata of a show with the one provided.
-    /// Returns whether last_updated field has been updated.
-    pub fn update_show(&mut self, show: Show) -> bool {
-        // Find show in user data
-        let subscribed_shows = &mut self.data.subscribed_shows;
-        let index = match subscribed_shows.iter().position(|elem| elem.id == show.id) {
-            Some(index) => index,
-            None => return false,
-        };
-        let stored_show = &mut subscribed_shows[index];
+    pub fn add_show(&mut self, show: Show) {
+        if !self.data.subscribed_shows.contains(&show) {
+            self.data.subscribed_shows.push(show);
 
-        // Update name
-        if stored_show.name != show.name {
-            println!("\"{}\" changed to \"{}\"", stored_show.name, show.name);
-            stored_show.name = show.name;
+            self.data.subscribed_shows.sort();
         }
+    }
 
-        // Update language
-        stored_show.language = show.language;
-
-        // Update network
-        stored_show.network = show.network;
-
-        // Update web channel
-        stored_show.web_channel = show.web_channel;
-
-        // Update status
-        if stored_show.status != show.status {
-            println!(
-                "{}: Changed from {} to {}",
-                stored_show.name, stored_show.status, show.status
-            );
-            stored_show.status = show.status;
+    /// Assigns a viewing state to a subscribed show.
+    ///
+    /// Returns false if the show isn't subscribed.
+    pub fn set_viewing_state(&mut self, show_id: usize, viewing_state: ViewingState) -> bool {
+        match self
+            .data
+            .subscribed_shows
+            .iter_mut()
+            .find(|show| show.id == show_id)
+        {
+            Some(show) => {
+                show.viewing_state = viewing_state;
+                true
+            }
+            None => false,
         }
+    }
 
-        // Update runtime
-        stored_show.runtime = show.runtime;
+    /// Adds tags to a subscribed show. Tags are stored in lowercase.
+    ///
+    /// Returns false if the show isn't subscribed.
+    pub fn add_tags(&mut self, show_id: usize, tags: &[&str]) -> bool {
+        match self
+            .data
+            .subscribed_shows
+            .iter_mut()
+            .find(|show| show.id == show_id)
+        {
+            Some(show) => {
+                show.tags
+                    .extend(tags.iter().map(|tag| tag.trim().to_lowercase()));
+                show.tags.retain(|tag| !tag.is_empty());
+                show.tags.sort();
+                show.tags.dedup();
+                true
+            }
+            None => false,
+        }
+    }
 
-        // Update schedule
-        stored_show.schedule = show.schedule;
-
-        // Last updated
-        // TODO: also store previous_episode field and see if that has changed
-        if stored_show.last_updated != show.last_updated {
-            stored_show.last_updated = show.last_updated;
-            return true;
+    /// Removes tags from a subscribed show.
+    ///
+    /// Returns false if the show isn't subscribed.
+    pub fn remove_tags(&mut self, show_id: usize, tags: &[&str]) -> bool {
+        match self
+            .data
+            .subscribed_shows
+            .iter_mut()
+            .find(|show| show.id == show_id)
+        {
+            Some(show) => {
+                show.tags
+                    .retain(|tag| !tags.iter().any(|removed| removed.to_lowercase() == *tag));
+                true
+            }
+            None => false,
         }
-
-        false
     }
 
-    /// Updates the meta data of an episode with the one provided.
-    /// Returns true if episode has been found, false otherwise.
-    pub fn update_episode(&mut self, episode: &Episode) -> bool {
-        // Find episode in user data
-        let unwatched_episodes = &mut self.data.unwatched_episodes;
-        let index = match unwatched_episodes
-            .iter()
-            .position(|elem| elem.episode_id == episode.episode_id)
+    /// Attaches a note to a subscribed show.
+    ///
+    /// Returns false if the show isn't subscribed.
+    pub fn add_note(&mut self, show_id: usize, note: &str) -> bool {
+        match self
+            .data
+            .subscribed_shows
+            .iter_mut()
+            .find(|show| show.id == show_id)
         {
-            Some(index) => index,
-            None => return false,
-        };
-        let stored_episode = &mut unwatched_episodes[index];
-
-        // Update name
-        if stored_episode.name != episode.name {
-            println!(
-                "\"{}\" changed to \"{}\"",
-                stored_episode.name, episode.name
-            );
-            stored_episode.name = episode.name.clone();
-        }
-
-        // Update season / number
-        if stored_episode.season != episode.season || stored_episode.number != episode.number {
-            println!(
-                "{}: Changed from being season {} episode {} to season {} episode {}",
-                stored_episode.name,
-                stored_episode.season,
-                stored_episode.number,
-                episode.season,
-                episode.number
-            );
-            stored_episode.season = episode.season;
-            stored_episode.number = episode.number;
+            Some(show) => {
+                show.notes.push(note.to_string());
+                true
+            }
+            None => false,
         }
-
-        // Update airstamp
-        stored_episode.airstamp = episode.airstamp;
-
-        // Update runtime
-        stored_episode.runtime = episode.runtime;
-
-        true
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use chrono::{TimeZone, Utc};
 
-    use super::*;
-    use tvmaze_api::{Day, Network, Schedule, Status};
-
-    fn star_trek_discovery() -> Show {
-        Show {
-            id: 7480,
-            name: "Star Trek: Discovery".to_string(),
-            language: Some("English".to_string()),
-            network: None,
-            web_channel: Some(Network {
-                id: 107,
-                name: "CBS All Access".to_string(),
-            }),
-            status: Status::Running,
-            runtime: Some(60),
-            schedule: Schedule {
-                days: vec![Day::Sunday],
-            },
-            last_updated: 0,
-            last_watched_episode: (0, 0),
+    /// Deletes a note of a subscribed show, numbered from 1 as in `info`.
+    ///
+    /// Returns false if the show isn't subscribed or doesn't have a note with this number.
+    pub fn delete_note(&mut self, show_id: usize, number: usize) -> bool {
+        match self
+            .data
+            .subscribed_shows
+            .iter_mut()
+            .find(|show| show.id == show_id)
+        {
+            Some(ref mut show) if number > 0 && number <= show.notes.len() => {
+                show.notes.remove(number - 1);
+                true
+            }
+            _ => false,
         }
     }
 
-    fn the_orville() -> Show {
-        Show {
-            id: 20263,
-            name: "The Orville".to_string(),
-            language: Some("English".to_string()),
-            network: Some(Network {
-                id: 4,
-                name: "FOX".to_string(),
-            }),
-            web_channel: None,
-            status: Status::Running,
-            runtime: Some(60),
-            schedule: Schedule {
-                days: vec![Day::Thursday],
+    /// Sets a custom field of a subscribed show, or removes it if no value is given.
+    ///
+    /// Returns false if the show isn't subscribed or the field to remove doesn't exist.
+    pub fn set_field(&mut self, show_id: usize, key: &str, value: Option<&str>) -> bool {
+        match self
+            .data
+            .subscribed_shows
+            .iter_mut()
+            .find(|show| show.id == show_id)
+        {
+            Some(show) => match value {
+                Some(value) => {
+                    show.fields.insert(key.to_string(), value.to_string());
+                    true
+                }
+                None => show.fields.remove(key).is_some(),
             },
-            last_updated: 0,
-            last_watched_episode: (0, 0),
+            None => false,
         }
     }
 
-    fn the_orville_ep1() -> Episode {
-        Episode {
-            episode_id: 1172410,
-            show_id: 20263,
-            name: "Old Wounds".to_string(),
-            season: 1,
-            number: 1,
-            airstamp: Some(Utc.ymd(2017, 9, 10).and_hms(0, 0, 0)),
-            runtime: 60,
-            watched: false,
+    /// Sets the priority of a subscribed show.
+    ///
+    /// Returns false if the show isn't subscribed.
+    pub fn set_priority(&mut self, show_id: usize, priority: i32) -> bool {
+        match self
+            .data
+            .subscribed_shows
+            .iter_mut()
+            .find(|show| show.id == show_id)
+        {
+            Some(show) => {
+                show.priority = priority;
+                true
+            }
+            None => false,
         }
     }
 
-    fn the_orville_ep2() -> Episode {
-        Episode {
-            episode_id: 1201556,
-            show_id: 20263,
-            name: "Command Performance".to_string(),
-            season: 1,
-            number: 2,
-            airstamp: Some(Utc.ymd(2017, 9, 17).and_hms(0, 0, 0)),
-            runtime: 60,
-            watched: false,
-        }
+    /// Runtime of an episode in minutes, falling back to the runtime of the show.
+    pub fn episode_runtime(&self, episode: &Episode) -> Option<usize> {
+        episode.runtime.or_else(|| {
+            self.data
+                .subscribed_shows
+                .iter()
+                .find(|show| show.id == episode.show_id)
+                .and_then(|show| show.runtime)
+        })
     }
 
-    fn the_orville_ep3() -> Episode {
-        Episode {
-            episode_id: 1201557,
-            show_id: 20263,
-            name: "About a Girl".to_string(),
-            season: 1,
-            number: 3,
-            airstamp: Some(Utc.ymd(2017, 9, 22).and_hms(1, 0, 0)),
-            runtime: 60,
-            watched: false,
+    /// Stores the number of episodes per season of all shows contained in `episodes`.
+    ///
+    /// `episodes` should contain all episodes of these shows, including unaired ones.
+    pub fn set_episode_counts(&mut self, episodes: &[Episode]) {
+        for show in &mut self.data.subscribed_shows {
+            if episodes.iter().any(|episode| episode.show_id == show.id) {
+                show.episode_counts = episode_counts(show.id, episodes);
+            }
         }
     }
 
-    fn the_orville_ep4() -> Episode {
-        Episode {
-            episode_id: 1201558,
-            show_id: 20263,
-            name: "If the Stars Should Appear".to_string(),
-            season: 1,
-            number: 4,
-            airstamp: Some(Utc.ymd(2017, 9, 29).and_hms(1, 0, 0)),
-            runtime: 60,
-            watched: false,
-        }
+    /// Progress of each season of the given show.
+    ///
+    /// Episodes up to the last watched pointer count as watched, as well as watched episodes
+    /// after a gap. All other episodes that have aired are in the list of unwatched episodes.
+    pub fn season_progress(&self, show: &Show) -> Vec<SeasonProgress> {
+        let now = Utc::now();
+        let (last_season, last_number) = show.last_watched_episode;
+
+        let episodes: Vec<&Episode> = self
+            .data
+            .unwatched_episodes
+            .iter()
+            .filter(|episode| episode.show_id == show.id)
+            .collect();
+
+        let mut seasons: Vec<usize> = show
+            .episode_counts
+            .keys()
+            .cloned()
+            .chain(episodes.iter().map(|episode| episode.season))
+            .collect();
+        seasons.sort();
+        seasons.dedup();
+
+        seasons
+            .into_iter()
+            .map(|season| {
+                let season_episodes = episodes.iter().filter(|episode| episode.season == season);
+                let aired_unwatched = season_episodes
+                    .clone()
+                    .filter(|episode| !episode.watched)
+                    .filter(|episode| match episode.airstamp {
+                        Some(airstamp) => airstamp <= now,
+                        None => false,
+                    })
+                    .count();
+                let watched_after_gap = season_episodes.filter(|episode| episode.watched).count();
+
+                let total = show.episode_counts.get(&season).cloned().unwrap_or(0);
+                let watched_before_pointer = if season < last_season {
+                    total
+                } else if season == last_season {
+                    min(last_number, total)
+                } else {
+                    0
+                };
+                let watched = watched_before_pointer + watched_after_gap;
+                let aired = watched + aired_unwatched;
+
+                SeasonProgress {
+                    season,
+                    watched,
+                    aired,
+                    // Counts are only known after the next update for shows added earlier
+                    total: max(total, aired),
+                }
+            })
+            .collect()
     }
 
-    fn the_orville_season2_ep1() -> Episode {
-        Episode {
-            episode_id: 15151515,
-            show_id: 20263,
-            name: "Future Episode".to_string(),
-            season: 2,
-            number: 1,
-            airstamp: Some(Utc.ymd(2018, 3, 15).and_hms(0, 0, 0)),
-            runtime: 60,
-            watched: false,
+    pub fn add_episodes(&mut self, episodes: Vec<Episode>) {
+        let mut episode_added = false;
+        for episode in episodes {
+            if !self.data.unwatched_episodes.contains(&episode) {
+                self.data.unwatched_episodes.push(episode);
+                episode_added = true;
+            }
         }
-    }
 
-    fn star_trek_discovery_ep1() -> Episode {
-        Episode {
-            episode_id: 892064,
-            show_id: 7480,
-            name: "The Vulcan Hello".to_string(),
-            season: 1,
-            number: 1,
-            airstamp: Some(Utc.ymd(2017, 9, 25).and_hms(0, 30, 0)),
-            runtime: 60,
-            watched: false,
+        if episode_added {
+            self.data.unwatched_episodes.sort();
         }
     }
 
-    fn load_dev_user_data() -> UserData {
-        let mut user_data_path = get_data_root(AppDataType::UserData).unwrap();
-        user_data_path.push("bingers_dev");
-        assert!(user_data_path.ends_with("bingers_dev"));
-
-        UserData::new(user_data_path)
-    }
+    /// Merges a show and its unwatched episodes, e.g. from another machine, into the user data.
+    ///
+    /// Shows that aren't subscribed yet are added as they are. For subscribed shows, the furthest
+    /// last watched pointer wins and watched flags of unwatched episodes are combined. Tags, notes
+    /// and custom fields are added, all other settings of the subscribed show are kept.
+    pub fn merge_show(&mut self, show: Show, episodes: Vec<Episode>) {
+        let show_id = show.id;
+        let episodes = episodes
+            .into_iter()
+            .filter(|episode| episode.show_id == show_id);
+
+        let index = match self
+            .data
+            .subscribed_shows
+            .iter()
+            .position(|stored_show| stored_show.id == show_id)
+        {
+            Some(index) => index,
+            None => {
+                self.add_show(show);
+                self.add_episodes(episodes.collect());
+                return;
+            }
+        };
 
-    #[test]
-    fn version() {
-        let user_data = load_dev_user_data();
-        assert_eq!(1, user_data.version());
-    }
+        let mut last_watched = {
+            let stored_show = &mut self.data.subscribed_shows[index];
+            stored_show.last_watched_episode =
+                max(stored_show.last_watched_episode, show.last_watched_episode);
 
-    #[test]
-    fn add_new_show() {
-        let mut user_data = load_dev_user_data();
-        assert!(user_data.subscribed_shows().is_empty());
+            for tag in show.tags {
+                if !stored_show.tags.contains(&tag) {
+                    stored_show.tags.push(tag);
+                }
+            }
+            stored_show.tags.sort();
+            for note in show.notes {
+                if !stored_show.notes.contains(&note) {
+                    stored_show.notes.push(note);
+                }
+            }
+            for (key, value) in show.fields {
+                stored_show.fields.entry(key).or_insert(value);
+            }
+            if stored_show.episode_counts.is_empty() {
+                stored_show.episode_counts = show.episode_counts;
+            }
 
-        user_data.add_show(star_trek_discovery());
-        assert!(user_data
-            .subscribed_shows()
-            .contains(&star_trek_discovery()));
-    }
+            stored_show.last_watched_episode
+        };
 
-    #[test]
-    fn do_not_add_already_subscribed_show() {
-        let mut user_data = load_dev_user_data();
-        user_data.add_show(star_trek_discovery());
-        user_data.add_show(the_orville());
+        for episode in episodes {
+            match self
+                .data
+                .unwatched_episodes
+                .iter_mut()
+                .find(|unwatched| **unwatched == episode)
+            {
+                Some(unwatched) => unwatched.watched |= episode.watched,
+                None => self.data.unwatched_episodes.push(episode),
+            }
+        }
+        self.data.unwatched_episodes.sort();
 
-        assert!(user_data
-            .subscribed_shows()
-            .contains(&star_trek_discovery()));
-        assert!(user_data.subscribed_shows().contains(&the_orville()));
-        assert_eq!(2, user_data.subscribed_shows().len());
+        // Move the last watched pointer over watched episodes up to the first gap
+        if let Some(last) = self
+            .data
+            .unwatched_episodes
+            .iter()
+            .filter(|episode| {
+                episode.show_id == show_id && episode_is_greater_than(episode, last_watched)
+            })
+            .take_while(|episode| episode.watched)
+            .map(|episode| (episode.season, episode.number))
+            .last()
+        {
+            last_watched = last;
+        }
 
-        user_data.add_show(the_orville());
-        assert_eq!(2, user_data.subscribed_shows().len());
+        self.data.unwatched_episodes.retain(|episode| {
+            episode.show_id != show_id || episode_is_greater_than(episode, last_watched)
+        });
+        self.data.subscribed_shows[index].last_watched_episode = last_watched;
     }
 
-    #[test]
+    /// Merges other user data, e.g. from another machine, into this one.
+    ///
+    /// Shows subscribed on both sides are merged with `merge_show()`, the history with
+    /// `merge_history()`. Conflicts are passed to `resolve`, which returns true to take over
+    /// the state of the other user data (add or remove the show, take its viewing state).
+    pub fn merge<F>(&mut self, other: UserData, mut resolve: F) -> Result<()>
+    where
+        F: FnMut(MergeConflict) -> Result<bool>,
+    {
+        let UserDataV1 {
+            subscribed_shows: other_shows,
+            unwatched_episodes: mut other_episodes,
+            history: other_history,
+            last_digest: other_last_digest,
+            digested_episodes: other_digested_episodes,
+            ..
+        } = other.data;
+        let other_ids: Vec<usize> = other_shows.iter().map(|show| show.id).collect();
+
+        for other_show in other_shows {
+            let episodes: Vec<Episode> = {
+                let (episodes, rest) = other_episodes
+                    .into_iter()
+                    .partition(|episode| episode.show_id == other_show.id);
+                other_episodes = rest;
+                episodes
+            };
+
+            let take_viewing_state = match self
+                .data
+                .subscribed_shows
+                .iter()
+                .find(|show| show.id == other_show.id)
+            {
+                Some(show) => {
+                    show.viewing_state != other_show.viewing_state
+                        && resolve(MergeConflict::ViewingState(show, &other_show.viewing_state))?
+                }
+                None => {
+                    if !resolve(MergeConflict::OnlyInOther(&other_show))? {
+                        continue;
+                    }
+                    false
+                }
+            };
+
+            if take_viewing_state {
+                self.set_viewing_state(other_show.id, other_show.viewing_state.clone());
+            }
+            self.merge_show(other_show, episodes);
+        }
+
+        let only_here: Vec<Show> = self
+            .data
+            .subscribed_shows
+            .iter()
+            .filter(|show| !other_ids.contains(&show.id))
+            .cloned()
+            .collect();
+        for show in only_here {
+            if resolve(MergeConflict::OnlyHere(&show))? {
+                self.remove_episodes(&show);
+                self.remove_show(&show);
+            }
+        }
+
+        self.merge_history(other_history);
+        self.data.last_digest = max(self.data.last_digest, other_last_digest);
+        if let Some(other_digested_episodes) = other_digested_episodes {
+            let digested = self.data.digested_episodes.get_or_insert_with(Vec::new);
+            for episode_id in other_digested_episodes {
+                if !digested.contains(&episode_id) {
+                    digested.push(episode_id);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves a merge conflict by comparing both sides with this user data as their common
+    /// base (three-way merge): the side that has changed since the base wins.
+    ///
+    /// Returns `None` if both sides have changed, so that the conflict has to be resolved
+    /// otherwise.
+    pub fn resolve_conflict(&self, conflict: &MergeConflict) -> Option<bool> {
+        let base_show = |show_id: usize| {
+            self.data
+                .subscribed_shows
+                .iter()
+                .find(|show| show.id == show_id)
+        };
+
+        match *conflict {
+            // Added there, or removed here
+            MergeConflict::OnlyInOther(show) => Some(base_show(show.id).is_none()),
+            // Removed there, or added here
+            MergeConflict::OnlyHere(show) => Some(base_show(show.id).is_some()),
+            MergeConflict::ViewingState(show, viewing_state) => {
+                match base_show(show.id).map(|base| &base.viewing_state) {
+                    Some(base) if *base == show.viewing_state => Some(true),
+                    Some(base) if base == viewing_state => Some(false),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Adds history entries of episodes that aren't part of the watch history yet.
+    ///
+    /// Returns the number of added entries.
+    pub fn merge_history(&mut self, history: Vec<WatchedEpisode>) -> usize {
+        let known: Vec<usize> = self
+            .data
+            .history
+            .iter()
+            .map(|entry| entry.episode_id)
+            .collect();
+        let count = self.data.history.len();
+
+        self.data.history.extend(
+            history
+                .into_iter()
+                .filter(|entry| !known.contains(&entry.episode_id)),
+        );
+        self.data.history.sort_by_key(|entry| entry.watched);
+
+        self.data.history.len() - count
+    }
+
+    /// Replaces all subscribed shows, unwatched episodes and the watch history.
+    pub fn replace(
+        &mut self,
+        mut shows: Vec<Show>,
+        mut episodes: Vec<Episode>,
+        history: Vec<WatchedEpisode>,
+    ) {
+        shows.sort();
+        episodes.sort();
+        self.data.subscribed_shows = shows;
+        self.data.unwatched_episodes = episodes;
+        self.data.history = history;
+    }
+
+    /// Marks episodes of a show as watched, e.g. when importing from another service.
+    ///
+    /// `episodes` should contain all aired episodes of the show. The show is subscribed, if it
+    /// isn't yet. Episodes that have already been watched stay watched, newly watched episodes
+    /// with a known point in time are recorded in the watch history.
+    ///
+    /// Returns the number of newly watched episodes.
+    pub fn import_watched(
+        &mut self,
+        show: Show,
+        mut episodes: Vec<Episode>,
+        watched: &[(EpisodeNumber, Option<DateTime<Utc>>)],
+    ) -> usize {
+        let show_id = show.id;
+        self.add_show(show);
+        let index = match self
+            .data
+            .subscribed_shows
+            .iter()
+            .position(|show| show.id == show_id)
+        {
+            Some(index) => index,
+            None => return 0,
+        };
+        let last_watched = self.data.subscribed_shows[index].last_watched_episode;
+
+        episodes.retain(|episode| episode.show_id == show_id);
+        episodes.sort();
+
+        let mut newly_watched = 0;
+        for episode in &mut episodes {
+            let already_watched = episode.watched
+                || !episode_is_greater_than(episode, last_watched)
+                || self
+                    .data
+                    .unwatched_episodes
+                    .iter()
+                    .any(|unwatched| unwatched == &*episode && unwatched.watched);
+            let imported = watched
+                .iter()
+                .find(|&&(number, _)| number == (episode.season, episode.number));
+
+            episode.watched = already_watched || imported.is_some();
+            if let (false, Some(&(_, when))) = (already_watched, imported) {
+                newly_watched += 1;
+                if let Some(when) = when {
+                    self.data.history.push(WatchedEpisode::new(episode, when));
+                }
+            }
+        }
+        // Imported episodes may have been watched before the most recent history entries
+        self.data.history.sort_by_key(|entry| entry.watched);
+
+        // Watched episodes up to the first gap are behind the last watched pointer
+        let new_last_watched = episodes
+            .iter()
+            .take_while(|episode| episode.watched)
+            .last()
+            .map_or(last_watched, |episode| {
+                max(last_watched, (episode.season, episode.number))
+            });
+
+        self.data
+            .unwatched_episodes
+            .retain(|episode| !(episode.show_id == show_id && episodes.contains(episode)));
+        self.data.unwatched_episodes.extend(
+            episodes
+                .into_iter()
+                .filter(|episode| episode_is_greater_than(episode, new_last_watched)),
+        );
+        self.data.unwatched_episodes.sort();
+        self.data.subscribed_shows[index].last_watched_episode = new_last_watched;
+
+        newly_watched
+    }
+
+    pub fn remove_episodes(&mut self, show: &Show) {
+        self.data
+            .unwatched_episodes
+            .retain(|episode| episode.show_id != show.id);
+    }
+
+    pub fn remove_show(&mut self, show: &Show) {
+        self.data
+            .subscribed_shows
+            .retain(|subscribed_show| subscribed_show != show);
+    }
+
+    /// Mark episode of given show as watched.
+    ///
+    /// If neither season nor episode are specified, will mark the next unwatched episode
+    /// as watched.
+    ///
+    /// If only season is specified, will mark the whole season as watched.
+    ///
+    /// If both season and episode are specified, will mark the exact episode as watched.
+    ///
+    /// Marked episodes are added to the watch history.
+    ///
+    /// Returns episode number of last episode that was marked as watched.
+    pub fn mark_as_watched(
+        &mut self,
+        show_id: usize,
+        season: Option<usize>,
+        episode: Option<usize>,
+    ) -> Option<(usize, usize)> {
+        let unwatched: Vec<usize> = self
+            .data
+            .unwatched_episodes
+            .iter()
+            .filter(|episode| episode.show_id == show_id && !episode.watched)
+            .map(|episode| episode.episode_id)
+            .collect();
+
+        // Mark episode(s) as watched
+        let last_marked = match (season, episode) {
+            (Some(season), None) => self.mark_season_as_watched(show_id, season),
+            (Some(season), Some(episode)) => self.mark_episode_as_watched(show_id, season, episode),
+            (None, None) => self.mark_next_episode_as_watched(show_id),
+            (None, Some(_)) => None,
+        };
+
+        if let Some(last_marked) = last_marked {
+            // Record marked episodes in watch history
+            let now = Utc::now();
+            for episode in self.data.unwatched_episodes.iter().filter(|episode| {
+                episode.show_id == show_id
+                    && episode.watched
+                    && unwatched.contains(&episode.episode_id)
+            }) {
+                self.data.history.push(WatchedEpisode::new(episode, now));
+            }
+
+            let mut gap = false;
+            let mut last_watched = (0, 0);
+            let mut show_index = None;
+
+            // Determine last watched episode (or rather the episode before the previously
+            // first unwatched episode)
+            for (i, show) in self
+                .data
+                .subscribed_shows
+                .iter()
+                .enumerate()
+                .filter(|&(_, show)| show.id == show_id)
+            {
+                last_watched = show.last_watched_episode;
+                show_index = Some(i);
+            }
+
+            // Determine if there are unwatched episodes between the last watched episode
+            // and the episodes that were now marked as watched.
+            for _ in self
+                .data
+                .unwatched_episodes
+                .iter()
+                .filter(|episode| episode.show_id == show_id && !episode.watched)
+                .filter(|episode| episode_is_greater_than(episode, last_watched))
+                .filter(|episode| episode_is_less_than(episode, last_marked))
+            {
+                gap = true;
+            }
+
+            // Remove all watched episodes and update last watched episode.
+            //
+            // Don't remove watched episodes that are separated by the last_watched pointer with
+            // a gap of unwatched episodes.
+            //
+            // Cleans up watched episodes if gap is eliminated.
+            if !gap {
+                let mut last_watched = last_watched;
+                let mut stop = false;
+
+                // This is slightly dirty because it depends on the internal implementation
+                // of retain() (i.e. that the vector is iterated over in order from start to end).
+                // Tests should catch it, if that implementation ever should change...
+                self.data.unwatched_episodes.retain(|episode| {
+                    if episode.show_id == show_id && episode_is_greater_than(episode, last_watched)
+                    {
+                        // If the episode is marked as watched and we haven't yet hit a gap...
+                        if episode.watched && !stop {
+                            // ... update last_watched pointer and remove episode
+                            last_watched = (episode.season, episode.number);
+                            false
+                        } else {
+                            // We hit a gap. Retain all following episodes.
+                            stop = true;
+                            true
+                        }
+                    } else {
+                        // Keep episodes of other shows
+                        true
+                    }
+                });
+
+                if let Some(index) = show_index {
+                    self.data.subscribed_shows[index].last_watched_episode = last_watched;
+                }
+            }
+        }
+
+        last_marked
+    }
+
+    /// Mark episode of given show as unwatched.
+    ///
+    /// If neither season nor episode are specified, will mark the most recently watched
+    /// episode as unwatched.
+    ///
+    /// If only season is specified, will mark the whole season as unwatched.
+    ///
+    /// If both season and episode are specified, will mark the exact episode as unwatched.
+    ///
+    /// Episodes before the last watched pointer have already been removed from the list of
+    /// unwatched episodes, so they are restored from `episodes`, which should contain all aired
+    /// episodes of the show. Restored episodes that are still watched are kept after the gap.
+    ///
+    /// Returns episode number of first episode that was marked as unwatched.
+    pub fn mark_as_unwatched(
+        &mut self,
+        show_id: usize,
+        season: Option<usize>,
+        episode: Option<usize>,
+        episodes: Vec<Episode>,
+    ) -> Option<(usize, usize)> {
+        let show_index = self
+            .data
+            .subscribed_shows
+            .iter()
+            .position(|show| show.id == show_id)?;
+        let last_watched = self.data.subscribed_shows[show_index].last_watched_episode;
+
+        let mut episodes: Vec<Episode> = episodes
+            .into_iter()
+            .filter(|episode| episode.show_id == show_id)
+            .collect();
+        episodes.sort();
+
+        let is_known = |number: EpisodeNumber| {
+            episodes
+                .iter()
+                .chain(self.data.unwatched_episodes.iter())
+                .any(|episode| {
+                    episode.show_id == show_id && (episode.season, episode.number) == number
+                })
+        };
+
+        // Determine episodes to mark as unwatched
+        let mut targets: Vec<EpisodeNumber> = match (season, episode) {
+            (Some(season), Some(episode)) => vec![(season, episode)],
+            (Some(season), None) => episodes
+                .iter()
+                .chain(self.data.unwatched_episodes.iter())
+                .filter(|episode| episode.show_id == show_id && episode.season == season)
+                .map(|episode| (episode.season, episode.number))
+                .collect(),
+            (None, None) => {
+                // Most recently watched episode is either a watched episode after a gap or
+                // the last watched pointer
+                let watched_after_gap = self
+                    .data
+                    .unwatched_episodes
+                    .iter()
+                    .filter(|episode| episode.show_id == show_id && episode.watched)
+                    .map(|episode| (episode.season, episode.number))
+                    .max();
+
+                match watched_after_gap {
+                    Some(number) => vec![number],
+                    None if last_watched != (0, 0) => vec![last_watched],
+                    None => vec![],
+                }
+            }
+            (None, Some(_)) => vec![],
+        };
+        targets.retain(|&number| is_known(number));
+        targets.sort();
+        targets.dedup();
+
+        let first = *targets.first()?;
+        let mut changed = false;
+
+        // Undo the most recent watch of every target that is currently watched
+        let watched_targets: Vec<EpisodeNumber> = targets
+            .iter()
+            .cloned()
+            .filter(|&number| {
+                number <= last_watched
+                    || self.data.unwatched_episodes.iter().any(|episode| {
+                        episode.show_id == show_id
+                            && episode.watched
+                            && (episode.season, episode.number) == number
+                    })
+            })
+            .collect();
+        for (season, number) in watched_targets {
+            if let Some(index) = self.data.history.iter().rposition(|entry| {
+                entry.show_id == show_id && (entry.season, entry.number) == (season, number)
+            }) {
+                self.data.history.remove(index);
+            }
+        }
+
+        // Move last watched pointer before the first episode and restore removed episodes
+        if first <= last_watched {
+            let new_last_watched = episodes
+                .iter()
+                .map(|episode| (episode.season, episode.number))
+                .filter(|&number| number < first)
+                .max()
+                .unwrap_or((0, 0));
+
+            for mut episode in episodes.into_iter().filter(|episode| {
+                episode_is_greater_than(episode, new_last_watched)
+                    && !episode_is_greater_than(episode, last_watched)
+            }) {
+                if !self.data.unwatched_episodes.contains(&episode) {
+                    episode.watched = !targets.contains(&(episode.season, episode.number));
+                    self.data.unwatched_episodes.push(episode);
+                }
+            }
+
+            self.data.subscribed_shows[show_index].last_watched_episode = new_last_watched;
+            self.data.unwatched_episodes.sort();
+            changed = true;
+        }
+
+        // Unmark watched episodes after the last watched pointer
+        for episode in self.data.unwatched_episodes.iter_mut().filter(|episode| {
+            episode.show_id == show_id
+                && episode.watched
+                && targets.contains(&(episode.season, episode.number))
+        }) {
+            episode.watched = false;
+            changed = true;
+        }
+
+        if changed {
+            Some(first)
+        } else {
+            None
+        }
+    }
+
+    // See clippy issue #3159 (https://github.com/rust-lang-nursery/rust-clippy/issues/3159)
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::never_loop))]
+    fn mark_next_episode_as_watched(&mut self, show_id: usize) -> Option<(usize, usize)> {
+        let mut marked = None;
+
+        for episode in self
+            .data
+            .unwatched_episodes
+            .iter_mut()
+            .filter(|episode| episode.show_id == show_id && !episode.watched)
+        {
+            episode.watched = true;
+            marked = Some((episode.season, episode.number));
+            break;
+        }
+
+        marked
+    }
+
+    fn mark_episode_as_watched(
+        &mut self,
+        show_id: usize,
+        season: usize,
+        number: usize,
+    ) -> Option<(usize, usize)> {
+        let mut marked = None;
+
+        for episode in self.data.unwatched_episodes.iter_mut().filter(|episode| {
+            episode.show_id == show_id
+                && episode.season == season
+                && episode.number == number
+                && !episode.watched
+        }) {
+            episode.watched = true;
+            marked = Some((episode.season, episode.number));
+        }
+
+        marked
+    }
+
+    fn mark_season_as_watched(&mut self, show_id: usize, season: usize) -> Option<(usize, usize)> {
+        let mut marked = None;
+
+        for episode in self.data.unwatched_episodes.iter_mut().filter(|episode| {
+            episode.show_id == show_id && episode.season == season && !episode.watched
+        }) {
+            episode.watched = true;
+            marked = Some((episode.season, episode.number));
+        }
+
+        marked
+    }
+
+    /// Updates the metadata of a show with the one provided. Descriptions of noteworthy
+    /// changes (name, status) are appended to `changes`.
+    /// Returns whether last_updated field has been updated.
+    pub fn update_show(&mut self, show: Show, changes: &mut Vec<String>) -> bool {
+        // Find show in user data
+        let subscribed_shows = &mut self.data.subscribed_shows;
+        let index = match subscribed_shows.iter().position(|elem| elem.id == show.id) {
+            Some(index) => index,
+            None => return false,
+        };
+        let stored_show = &mut subscribed_shows[index];
+
+        // Update name
+        if stored_show.name != show.name {
+            changes.push(format!(
+                "\"{}\" changed to \"{}\"",
+                stored_show.name, show.name
+            ));
+            stored_show.name = show.name;
+        }
+
+        // Update language
+        stored_show.language = show.language;
+
+        // Update network
+        stored_show.network = show.network;
+
+        // Update web channel
+        stored_show.web_channel = show.web_channel;
+
+        // Update status
+        if stored_show.status != show.status {
+            changes.push(format!(
+                "{}: Changed from {} to {}",
+                stored_show.name, stored_show.status, show.status
+            ));
+            self.data.status_changes.push(StatusChange {
+                show_id: stored_show.id,
+                previous: stored_show.status.clone(),
+                current: show.status.clone(),
+                changed: Utc::now(),
+            });
+            stored_show.status = show.status;
+        }
+
+        // Update runtime
+        stored_show.runtime = show.runtime;
+
+        // Update premiere date, genres, summary and ids on other services
+        stored_show.premiered = show.premiered;
+        stored_show.genres = show.genres;
+        stored_show.summary = show.summary;
+        stored_show.externals = show.externals;
+
+        // Update schedule
+        stored_show.schedule = show.schedule;
+
+        // Last updated
+        // TODO: also store previous_episode field and see if that has changed
+        if stored_show.last_updated != show.last_updated {
+            stored_show.last_updated = show.last_updated;
+            return true;
+        }
+
+        false
+    }
+
+    /// Updates the meta data of an episode with the one provided. Descriptions of noteworthy
+    /// changes (name, season / number) are appended to `changes`.
+    /// Returns true if episode has been found, false otherwise.
+    pub fn update_episode(&mut self, episode: &Episode, changes: &mut Vec<String>) -> bool {
+        // Find episode in user data
+        let unwatched_episodes = &mut self.data.unwatched_episodes;
+        let index = match unwatched_episodes
+            .iter()
+            .position(|elem| elem.episode_id == episode.episode_id)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let stored_episode = &mut unwatched_episodes[index];
+
+        // Update name
+        if stored_episode.name != episode.name {
+            changes.push(format!(
+                "\"{}\" changed to \"{}\"",
+                stored_episode.name, episode.name
+            ));
+            stored_episode.name = episode.name.clone();
+        }
+
+        // Update season / number
+        if stored_episode.season != episode.season || stored_episode.number != episode.number {
+            changes.push(format!(
+                "{}: Changed from being season {} episode {} to season {} episode {}",
+                stored_episode.name,
+                stored_episode.season,
+                stored_episode.number,
+                episode.season,
+                episode.number
+            ));
+            stored_episode.season = episode.season;
+            stored_episode.number = episode.number;
+        }
+
+        // Update airstamp
+        stored_episode.airstamp = episode.airstamp;
+
+        // Update runtime
+        stored_episode.runtime = episode.runtime;
+
+        true
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use tvmaze_api::{Day, Externals, Network, Schedule, Status, ViewingState};
+
+    /// JSON of a running show without network, as stored in the user data. `extra` fields are
+    /// added to the object, e.g. `"viewingState": "paused"`.
+    pub fn show_json(id: usize, name: &str, extra: &str) -> String {
+        format!(
+            r#"{{"id": {}, "name": "{}", "language": "English", "network": null,
+                "webChannel": null, "status": "Running", "runtime": 60,
+                "schedule": {{"days": []}}{}{}}}"#,
+            id,
+            name,
+            if extra.is_empty() { "" } else { ", " },
+            extra
+        )
+    }
+
+    /// Running show without network, see `show_json()`.
+    pub fn show(id: usize, name: &str) -> Show {
+        ::serde_json::from_str(&show_json(id, name, "")).unwrap()
+    }
+
+    fn star_trek_discovery() -> Show {
+        Show {
+            id: 7480,
+            name: "Star Trek: Discovery".to_string(),
+            language: Some("English".to_string()),
+            network: None,
+            web_channel: Some(Network {
+                id: 107,
+                name: "CBS All Access".to_string(),
+            }),
+            status: Status::Running,
+            runtime: Some(60),
+            premiered: None,
+            genres: Vec::new(),
+            summary: None,
+            externals: Externals::default(),
+            schedule: Schedule {
+                time: String::new(),
+                days: vec![Day::Sunday],
+            },
+            last_updated: 0,
+            last_watched_episode: (0, 0),
+            viewing_state: ViewingState::Watching,
+            tags: Vec::new(),
+            notes: Vec::new(),
+            fields: BTreeMap::new(),
+            priority: 0,
+            episode_counts: BTreeMap::new(),
+        }
+    }
+
+    fn the_orville() -> Show {
+        Show {
+            id: 20263,
+            name: "The Orville".to_string(),
+            language: Some("English".to_string()),
+            network: Some(Network {
+                id: 4,
+                name: "FOX".to_string(),
+            }),
+            web_channel: None,
+            status: Status::Running,
+            runtime: Some(60),
+            premiered: None,
+            genres: Vec::new(),
+            summary: None,
+            externals: Externals::default(),
+            schedule: Schedule {
+                time: String::new(),
+                days: vec![Day::Thursday],
+            },
+            last_updated: 0,
+            last_watched_episode: (0, 0),
+            viewing_state: ViewingState::Watching,
+            tags: Vec::new(),
+            notes: Vec::new(),
+            fields: BTreeMap::new(),
+            priority: 0,
+            episode_counts: BTreeMap::new(),
+        }
+    }
+
+    fn the_orville_ep1() -> Episode {
+        Episode {
+            episode_id: 1172410,
+            show_id: 20263,
+            name: "Old Wounds".to_string(),
+            season: 1,
+            number: 1,
+            airstamp: Some(Utc.ymd(2017, 9, 10).and_hms(0, 0, 0)),
+            runtime: 60,
+            watched: false,
+        }
+    }
+
+    fn the_orville_ep2() -> Episode {
+        Episode {
+            episode_id: 1201556,
+            show_id: 20263,
+            name: "Command Performance".to_string(),
+            season: 1,
+            number: 2,
+            airstamp: Some(Utc.ymd(2017, 9, 17).and_hms(0, 0, 0)),
+            runtime: 60,
+            watched: false,
+        }
+    }
+
+    fn the_orville_ep3() -> Episode {
+        Episode {
+            episode_id: 1201557,
+            show_id: 20263,
+            name: "About a Girl".to_string(),
+            season: 1,
+            number: 3,
+            airstamp: Some(Utc.ymd(2017, 9, 22).and_hms(1, 0, 0)),
+            runtime: 60,
+            watched: false,
+        }
+    }
+
+    fn the_orville_ep4() -> Episode {
+        Episode {
+            episode_id: 1201558,
+            show_id: 20263,
+            name: "If the Stars Should Appear".to_string(),
+            season: 1,
+            number: 4,
+            airstamp: Some(Utc.ymd(2017, 9, 29).and_hms(1, 0, 0)),
+            runtime: 60,
+            watched: false,
+        }
+    }
+
+    fn the_orville_season2_ep1() -> Episode {
+        Episode {
+            episode_id: 15151515,
+            show_id: 20263,
+            name: "Future Episode".to_string(),
+            season: 2,
+            number: 1,
+            airstamp: Some(Utc.ymd(2018, 3, 15).and_hms(0, 0, 0)),
+            runtime: 60,
+            watched: false,
+        }
+    }
+
+    fn star_trek_discovery_ep1() -> Episode {
+        Episode {
+            episode_id: 892064,
+            show_id: 7480,
+            name: "The Vulcan Hello".to_string(),
+            season: 1,
+            number: 1,
+            airstamp: Some(Utc.ymd(2017, 9, 25).and_hms(0, 30, 0)),
+            runtime: 60,
+            watched: false,
+        }
+    }
+
+    fn load_dev_user_data() -> UserData {
+        let mut user_data_path = get_data_root(AppDataType::UserData).unwrap();
+        user_data_path.push("bingers_dev");
+        assert!(user_data_path.ends_with("bingers_dev"));
+
+        UserData::new(user_data_path)
+    }
+
+    #[test]
+    fn version() {
+        let user_data = load_dev_user_data();
+        assert_eq!(1, user_data.version());
+    }
+
+    #[test]
+    fn add_new_show() {
+        let mut user_data = load_dev_user_data();
+        assert!(user_data.subscribed_shows().is_empty());
+
+        user_data.add_show(star_trek_discovery());
+        assert!(user_data
+            .subscribed_shows()
+            .contains(&star_trek_discovery()));
+    }
+
+    #[test]
+    fn do_not_add_already_subscribed_show() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(star_trek_discovery());
+        user_data.add_show(the_orville());
+
+        assert!(user_data
+            .subscribed_shows()
+            .contains(&star_trek_discovery()));
+        assert!(user_data.subscribed_shows().contains(&the_orville()));
+        assert_eq!(2, user_data.subscribed_shows().len());
+
+        user_data.add_show(the_orville());
+        assert_eq!(2, user_data.subscribed_shows().len());
+    }
+
+    #[test]
     fn remove_show() {
         let mut user_data = load_dev_user_data();
         user_data.add_show(star_trek_discovery());
         user_data.add_show(the_orville());
 
         assert!(user_data
-            .subscribed_shows()
-            .contains(&star_trek_discovery()));
-        assert!(user_data.subscribed_shows().contains(&the_orville()));
+            .subscribed_shows()
+            .contains(&star_trek_discovery()));
+        assert!(user_data.subscribed_shows().contains(&the_orville()));
+
+        user_data.remove_show(&star_trek_discovery());
+
+        assert!(!user_data
+            .subscribed_shows()
+            .contains(&star_trek_discovery()));
+        assert!(user_data.subscribed_shows().contains(&the_orville()));
+    }
+
+    #[test]
+    fn add_episode() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_episodes(vec![the_orville_ep1()]);
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&the_orville_ep1()));
+    }
+
+    #[test]
+    fn do_not_add_episode_twice() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_episodes(vec![the_orville_ep1()]);
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&the_orville_ep1()));
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+
+        user_data.add_episodes(vec![the_orville_ep1()]);
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+    }
+
+    #[test]
+    fn remove_episodes_of_a_given_show() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_show(star_trek_discovery());
+        user_data.add_episodes(vec![
+            the_orville_ep1(),
+            the_orville_ep2(),
+            star_trek_discovery_ep1(),
+        ]);
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&the_orville_ep1()));
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&the_orville_ep2()));
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&star_trek_discovery_ep1()));
+        assert_eq!(3, user_data.data.unwatched_episodes.len());
+
+        user_data.remove_episodes(&the_orville());
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&star_trek_discovery_ep1()));
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+    }
+
+    #[test]
+    fn mark_next_episode_as_unwatched() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_show(star_trek_discovery());
+        user_data.add_episodes(vec![
+            the_orville_ep1(),
+            the_orville_ep2(),
+            star_trek_discovery_ep1(),
+        ]);
+        assert_eq!(3, user_data.data.unwatched_episodes.len());
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(Some((1, 1)), user_data.mark_as_watched(20263, None, None));
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&the_orville_ep2()));
+        assert_eq!(2, user_data.data.unwatched_episodes.len());
+
+        assert_eq!(
+            (1, 1),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+
+        assert_eq!(Some((1, 2)), user_data.mark_as_watched(20263, None, None));
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&star_trek_discovery_ep1()));
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+
+        assert_eq!(
+            (1, 2),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+
+        assert_eq!(None, user_data.mark_as_watched(20263, None, None));
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert_eq!(
+            (1, 2),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+    }
+
+    #[test]
+    fn mark_season_as_unwatched() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_show(star_trek_discovery());
+        user_data.add_episodes(vec![
+            the_orville_ep1(),
+            the_orville_ep2(),
+            star_trek_discovery_ep1(),
+        ]);
+        assert_eq!(3, user_data.data.unwatched_episodes.len());
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(
+            Some((1, 2)),
+            user_data.mark_as_watched(20263, Some(1), None)
+        );
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&star_trek_discovery_ep1()));
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
 
-        user_data.remove_show(&star_trek_discovery());
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert_eq!(
+            (1, 2),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
 
-        assert!(!user_data
-            .subscribed_shows()
-            .contains(&star_trek_discovery()));
-        assert!(user_data.subscribed_shows().contains(&the_orville()));
+        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), None));
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&star_trek_discovery_ep1()));
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert_eq!(
+            (1, 2),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(None, user_data.mark_as_watched(20263, Some(2), None));
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+        assert_eq!(
+            (1, 2),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+    }
+
+    #[test]
+    fn mark_season_as_unwatched_with_gap() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_show(star_trek_discovery());
+        user_data.add_episodes(vec![
+            the_orville_ep1(),
+            the_orville_ep2(),
+            the_orville_season2_ep1(),
+            star_trek_discovery_ep1(),
+        ]);
+        assert_eq!(4, user_data.data.unwatched_episodes.len());
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(
+            Some((2, 1)),
+            user_data.mark_as_watched(20263, Some(2), None)
+        );
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert!(!user_data.data.unwatched_episodes[2].watched);
+        assert!(user_data.data.unwatched_episodes[3].watched);
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(None, user_data.mark_as_watched(20263, Some(2), None));
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert!(!user_data.data.unwatched_episodes[2].watched);
+        assert!(user_data.data.unwatched_episodes[3].watched);
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(None, user_data.mark_as_watched(20263, Some(3), None));
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert!(!user_data.data.unwatched_episodes[2].watched);
+        assert!(user_data.data.unwatched_episodes[3].watched);
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+    }
+
+    #[test]
+    fn mark_episode_as_unwatched() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_show(star_trek_discovery());
+        user_data.add_episodes(vec![
+            the_orville_ep1(),
+            the_orville_ep2(),
+            star_trek_discovery_ep1(),
+        ]);
+        assert_eq!(3, user_data.data.unwatched_episodes.len());
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(
+            Some((1, 1)),
+            user_data.mark_as_watched(20263, Some(1), Some(1))
+        );
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&the_orville_ep2()));
+        assert_eq!(2, user_data.data.unwatched_episodes.len());
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert_eq!(
+            (1, 1),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(
+            Some((1, 2)),
+            user_data.mark_as_watched(20263, Some(1), Some(2))
+        );
+
+        assert!(user_data
+            .data
+            .unwatched_episodes
+            .contains(&star_trek_discovery_ep1()));
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert_eq!(
+            (1, 2),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), Some(2)));
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+        assert_eq!(
+            (1, 2),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), Some(3)));
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+        assert_eq!(
+            (1, 2),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
     }
 
-    #[test]
-    fn add_episode() {
-        let mut user_data = load_dev_user_data();
-        user_data.add_show(the_orville());
-        user_data.add_episodes(vec![the_orville_ep1()]);
+    #[test]
+    fn mark_episode_as_unwatched_with_gap() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_show(star_trek_discovery());
+        user_data.add_episodes(vec![
+            the_orville_ep1(),
+            the_orville_ep2(),
+            star_trek_discovery_ep1(),
+        ]);
+        assert_eq!(3, user_data.data.unwatched_episodes.len());
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+
+        assert_eq!(
+            Some((1, 2)),
+            user_data.mark_as_watched(20263, Some(1), Some(2))
+        );
+
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert!(user_data.data.unwatched_episodes[2].watched);
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
 
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&the_orville_ep1()));
-    }
+        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), Some(2)));
 
-    #[test]
-    fn do_not_add_episode_twice() {
-        let mut user_data = load_dev_user_data();
-        user_data.add_show(the_orville());
-        user_data.add_episodes(vec![the_orville_ep1()]);
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert!(user_data.data.unwatched_episodes[2].watched);
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
 
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&the_orville_ep1()));
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
+        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), Some(3)));
 
-        user_data.add_episodes(vec![the_orville_ep1()]);
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
+        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert!(user_data.data.unwatched_episodes[2].watched);
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
     }
 
     #[test]
-    fn remove_episodes_of_a_given_show() {
+    fn keep_watched_episodes_after_gap() {
         let mut user_data = load_dev_user_data();
         user_data.add_show(the_orville());
         user_data.add_show(star_trek_discovery());
         user_data.add_episodes(vec![
             the_orville_ep1(),
             the_orville_ep2(),
+            the_orville_ep3(),
+            the_orville_ep4(),
             star_trek_discovery_ep1(),
         ]);
+        assert_eq!(5, user_data.data.unwatched_episodes.len());
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
 
+        assert_eq!(Some((1, 1)), user_data.mark_as_watched(20263, None, None));
+        assert_eq!(
+            (1, 1),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+        assert_eq!(4, user_data.data.unwatched_episodes.len());
+
+        assert_eq!(
+            Some((1, 4)),
+            user_data.mark_as_watched(20263, Some(1), Some(4))
+        );
+        assert_eq!(
+            (1, 1),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+        assert_eq!(4, user_data.data.unwatched_episodes.len());
+
+        assert_eq!(
+            Some((1, 2)),
+            user_data.mark_as_watched(20263, Some(1), Some(2))
+        );
+        assert_eq!(
+            (1, 2),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
         assert!(user_data
             .data
             .unwatched_episodes
-            .contains(&the_orville_ep1()));
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&the_orville_ep2()));
+            .contains(&the_orville_ep3()));
         assert!(user_data
             .data
             .unwatched_episodes
-            .contains(&star_trek_discovery_ep1()));
+            .contains(&the_orville_ep4()));
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert!(user_data.data.unwatched_episodes[2].watched);
         assert_eq!(3, user_data.data.unwatched_episodes.len());
-
-        user_data.remove_episodes(&the_orville());
-
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&star_trek_discovery_ep1()));
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
     }
 
     #[test]
-    fn mark_next_episode_as_unwatched() {
+    fn remove_other_watched_episodes_if_gap_is_eliminated() {
         let mut user_data = load_dev_user_data();
         user_data.add_show(the_orville());
         user_data.add_show(star_trek_discovery());
         user_data.add_episodes(vec![
             the_orville_ep1(),
             the_orville_ep2(),
+            the_orville_ep3(),
             star_trek_discovery_ep1(),
         ]);
-        assert_eq!(3, user_data.data.unwatched_episodes.len());
+        assert_eq!(4, user_data.data.unwatched_episodes.len());
         assert_eq!(
             (0, 0),
             user_data.data.subscribed_shows[1].last_watched_episode
         );
 
         assert_eq!(Some((1, 1)), user_data.mark_as_watched(20263, None, None));
-
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&the_orville_ep2()));
-        assert_eq!(2, user_data.data.unwatched_episodes.len());
-
         assert_eq!(
             (1, 1),
             user_data.data.subscribed_shows[1].last_watched_episode
         );
+        assert_eq!(3, user_data.data.unwatched_episodes.len());
 
-        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert_eq!(
+            Some((1, 3)),
+            user_data.mark_as_watched(20263, Some(1), Some(3))
+        );
+        assert_eq!(
+            (1, 1),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+        assert_eq!(3, user_data.data.unwatched_episodes.len());
         assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert!(user_data.data.unwatched_episodes[2].watched);
 
-        assert_eq!(Some((1, 2)), user_data.mark_as_watched(20263, None, None));
-
+        assert_eq!(
+            Some((1, 2)),
+            user_data.mark_as_watched(20263, Some(1), Some(2))
+        );
+        assert_eq!(
+            (1, 3),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
         assert!(user_data
             .data
             .unwatched_episodes
             .contains(&star_trek_discovery_ep1()));
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
+    }
+
+    fn the_orville_episodes() -> Vec<Episode> {
+        vec![
+            the_orville_ep1(),
+            the_orville_ep2(),
+            the_orville_ep3(),
+            the_orville_ep4(),
+        ]
+    }
 
+    #[test]
+    fn mark_last_watched_episode_as_unwatched() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_episodes(the_orville_episodes());
+        assert_eq!(
+            Some((1, 2)),
+            user_data.mark_as_watched(20263, Some(1), Some(2))
+        );
+        assert_eq!(Some((1, 1)), user_data.mark_as_watched(20263, None, None));
         assert_eq!(
             (1, 2),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            user_data.data.subscribed_shows[0].last_watched_episode
         );
+        assert_eq!(2, user_data.data.unwatched_episodes.len());
 
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-
-        assert_eq!(None, user_data.mark_as_watched(20263, None, None));
-
-        assert!(!user_data.data.unwatched_episodes[0].watched);
         assert_eq!(
-            (1, 2),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some((1, 2)),
+            user_data.mark_as_unwatched(20263, None, None, the_orville_episodes())
+        );
+        assert_eq!(
+            (1, 1),
+            user_data.data.subscribed_shows[0].last_watched_episode
         );
+        assert_eq!(3, user_data.data.unwatched_episodes.len());
+        assert_eq!(the_orville_ep2(), user_data.data.unwatched_episodes[0]);
+        assert!(!user_data.data.unwatched_episodes[0].watched);
     }
 
     #[test]
-    fn mark_season_as_unwatched() {
+    fn mark_episode_before_last_watched_as_unwatched() {
         let mut user_data = load_dev_user_data();
         user_data.add_show(the_orville());
         user_data.add_show(star_trek_discovery());
-        user_data.add_episodes(vec![
-            the_orville_ep1(),
-            the_orville_ep2(),
-            star_trek_discovery_ep1(),
-        ]);
-        assert_eq!(3, user_data.data.unwatched_episodes.len());
+        user_data.add_episodes(the_orville_episodes());
+        user_data.add_episodes(vec![star_trek_discovery_ep1()]);
         assert_eq!(
-            (0, 0),
+            Some((1, 4)),
+            user_data.mark_as_watched(20263, Some(1), None)
+        );
+        assert_eq!(
+            (1, 4),
             user_data.data.subscribed_shows[1].last_watched_episode
         );
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
 
+        // Episodes after the unwatched episode are kept as watched after the gap
         assert_eq!(
             Some((1, 2)),
-            user_data.mark_as_watched(20263, Some(1), None)
+            user_data.mark_as_unwatched(20263, Some(1), Some(2), the_orville_episodes())
         );
+        assert_eq!(
+            (1, 1),
+            user_data.data.subscribed_shows[1].last_watched_episode
+        );
+        assert_eq!(4, user_data.data.unwatched_episodes.len());
+        assert_eq!(the_orville_ep2(), user_data.data.unwatched_episodes[1]);
+        assert!(!user_data.data.unwatched_episodes[1].watched);
+        assert!(user_data.data.unwatched_episodes[2].watched);
+        assert!(user_data.data.unwatched_episodes[3].watched);
 
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&star_trek_discovery_ep1()));
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
-
-        assert!(!user_data.data.unwatched_episodes[0].watched);
+        // Closing the gap again removes the watched episodes
+        assert_eq!(Some((1, 2)), user_data.mark_as_watched(20263, None, None));
         assert_eq!(
-            (1, 2),
+            (1, 4),
             user_data.data.subscribed_shows[1].last_watched_episode
         );
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
+    }
 
-        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), None));
+    #[test]
+    fn mark_watched_season_as_unwatched() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_episodes(the_orville_episodes());
+        user_data.add_episodes(vec![the_orville_season2_ep1()]);
+        assert_eq!(
+            Some((1, 4)),
+            user_data.mark_as_watched(20263, Some(1), None)
+        );
+        assert_eq!(1, user_data.data.unwatched_episodes.len());
 
+        let mut episodes = the_orville_episodes();
+        episodes.push(the_orville_season2_ep1());
+        assert_eq!(
+            Some((1, 1)),
+            user_data.mark_as_unwatched(20263, Some(1), None, episodes)
+        );
+        assert_eq!(
+            (0, 0),
+            user_data.data.subscribed_shows[0].last_watched_episode
+        );
+        assert_eq!(5, user_data.data.unwatched_episodes.len());
         assert!(user_data
             .data
             .unwatched_episodes
-            .contains(&star_trek_discovery_ep1()));
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
+            .iter()
+            .all(|episode| !episode.watched));
+    }
 
-        assert!(!user_data.data.unwatched_episodes[0].watched);
+    #[test]
+    fn mark_watched_episode_after_gap_as_unwatched() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_episodes(the_orville_episodes());
         assert_eq!(
-            (1, 2),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some((1, 3)),
+            user_data.mark_as_watched(20263, Some(1), Some(3))
         );
+        assert!(user_data.data.unwatched_episodes[2].watched);
 
-        assert_eq!(None, user_data.mark_as_watched(20263, Some(2), None));
+        assert_eq!(
+            Some((1, 3)),
+            user_data.mark_as_unwatched(20263, None, None, the_orville_episodes())
+        );
+        assert!(!user_data.data.unwatched_episodes[2].watched);
+        assert_eq!(4, user_data.data.unwatched_episodes.len());
 
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
+        // Nothing left to mark as unwatched
         assert_eq!(
-            (1, 2),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            None,
+            user_data.mark_as_unwatched(20263, None, None, the_orville_episodes())
+        );
+        assert_eq!(
+            None,
+            user_data.mark_as_unwatched(20263, Some(1), Some(9), the_orville_episodes())
         );
     }
 
     #[test]
-    fn mark_season_as_unwatched_with_gap() {
+    fn record_watched_episodes_in_history() {
         let mut user_data = load_dev_user_data();
         user_data.add_show(the_orville());
-        user_data.add_show(star_trek_discovery());
-        user_data.add_episodes(vec![
-            the_orville_ep1(),
-            the_orville_ep2(),
-            the_orville_season2_ep1(),
-            star_trek_discovery_ep1(),
-        ]);
-        assert_eq!(4, user_data.data.unwatched_episodes.len());
-        assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
-        );
+        user_data.add_episodes(the_orville_episodes());
 
         assert_eq!(
-            Some((2, 1)),
-            user_data.mark_as_watched(20263, Some(2), None)
+            Some((1, 3)),
+            user_data.mark_as_watched(20263, Some(1), Some(3))
         );
-
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert!(!user_data.data.unwatched_episodes[1].watched);
-        assert!(!user_data.data.unwatched_episodes[2].watched);
-        assert!(user_data.data.unwatched_episodes[3].watched);
         assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some((1, 4)),
+            user_data.mark_as_watched(20263, Some(1), None)
         );
 
-        assert_eq!(None, user_data.mark_as_watched(20263, Some(2), None));
+        let history: Vec<_> = user_data
+            .history()
+            .iter()
+            .map(|entry| (entry.season, entry.number))
+            .collect();
+        assert_eq!(vec![(1, 3), (1, 1), (1, 2), (1, 4)], history);
 
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert!(!user_data.data.unwatched_episodes[1].watched);
-        assert!(!user_data.data.unwatched_episodes[2].watched);
-        assert!(user_data.data.unwatched_episodes[3].watched);
+        // Already watched episodes aren't recorded again
+        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), Some(3)));
+        assert_eq!(4, user_data.history().len());
+    }
+
+    #[test]
+    fn rate_watched_episode() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_episodes(the_orville_episodes());
         assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some((1, 1)),
+            user_data.mark_as_watched(20263, Some(1), Some(1))
         );
 
-        assert_eq!(None, user_data.mark_as_watched(20263, Some(3), None));
+        assert!(user_data.rate_episode(20263, 1, 1, Some(8), None));
+        assert!(user_data.rate_episode(20263, 1, 1, None, Some("great cold open".to_string())));
+        assert!(!user_data.rate_episode(20263, 1, 2, Some(5), None));
 
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert!(!user_data.data.unwatched_episodes[1].watched);
-        assert!(!user_data.data.unwatched_episodes[2].watched);
-        assert!(user_data.data.unwatched_episodes[3].watched);
+        let entry = &user_data.history()[0];
+        assert_eq!("Old Wounds", entry.name);
+        assert_eq!(Some(8), entry.rating);
+        assert_eq!(Some("great cold open".to_string()), entry.note);
+
+        // Unmark (which undoes the watch), watch and rate again
         assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some((1, 1)),
+            user_data.mark_as_unwatched(20263, Some(1), Some(1), the_orville_episodes())
+        );
+        assert_eq!(
+            Some((1, 1)),
+            user_data.mark_as_watched(20263, Some(1), Some(1))
         );
+        assert!(user_data.rate_episode(20263, 1, 1, Some(9), None));
+        assert_eq!(1, user_data.history().len());
+
+        let ratings = user_data.ratings(20263);
+        assert_eq!(1, ratings.len());
+        assert_eq!(Some(9), ratings[0].rating);
     }
 
     #[test]
-    fn mark_episode_as_unwatched() {
+    fn remove_history_when_marking_as_unwatched() {
         let mut user_data = load_dev_user_data();
         user_data.add_show(the_orville());
-        user_data.add_show(star_trek_discovery());
-        user_data.add_episodes(vec![
-            the_orville_ep1(),
-            the_orville_ep2(),
-            star_trek_discovery_ep1(),
-        ]);
-        assert_eq!(3, user_data.data.unwatched_episodes.len());
+        user_data.add_episodes(the_orville_episodes());
         assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some((1, 3)),
+            user_data.mark_as_watched(20263, Some(1), Some(3))
         );
-
         assert_eq!(
             Some((1, 1)),
             user_data.mark_as_watched(20263, Some(1), Some(1))
         );
+        // Second watch of episode 1 (e.g. recorded on another machine)
+        let mut rewatch = user_data.history()[1].clone();
+        rewatch.watched = rewatch.watched + ::chrono::Duration::days(1);
+        user_data.data.history.push(rewatch);
 
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&the_orville_ep2()));
-        assert_eq!(2, user_data.data.unwatched_episodes.len());
-
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert!(!user_data.data.unwatched_episodes[1].watched);
+        // Only the most recent watch of episode 1 is undone
         assert_eq!(
-            (1, 1),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some((1, 1)),
+            user_data.mark_as_unwatched(20263, Some(1), Some(1), the_orville_episodes())
         );
+        let history: Vec<EpisodeNumber> = user_data
+            .history()
+            .iter()
+            .map(|entry| (entry.season, entry.number))
+            .collect();
+        assert_eq!(vec![(1, 3), (1, 1)], history);
 
+        // Episodes that aren't watched (anymore) keep their history
         assert_eq!(
-            Some((1, 2)),
-            user_data.mark_as_watched(20263, Some(1), Some(2))
+            Some((1, 1)),
+            user_data.mark_as_unwatched(20263, Some(1), None, the_orville_episodes())
         );
+        let history: Vec<EpisodeNumber> = user_data
+            .history()
+            .iter()
+            .map(|entry| (entry.season, entry.number))
+            .collect();
+        assert_eq!(vec![(1, 1)], history);
+    }
 
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&star_trek_discovery_ep1()));
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
+    #[test]
+    fn keep_viewing_state_on_update() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
 
-        assert!(!user_data.data.unwatched_episodes[0].watched);
+        assert!(
+            user_data.set_viewing_state(20263, ViewingState::Dropped(Some("too slow".to_string())))
+        );
+        assert!(!user_data.set_viewing_state(7480, ViewingState::Paused));
+
+        let mut updated_show = the_orville();
+        updated_show.last_updated = 1;
+        let mut changes = Vec::new();
+        assert!(user_data.update_show(updated_show, &mut changes));
+        assert!(changes.is_empty());
         assert_eq!(
-            (1, 2),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            ViewingState::Dropped(Some("too slow".to_string())),
+            user_data.data.subscribed_shows[0].viewing_state
         );
+    }
 
-        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), Some(2)));
+    #[test]
+    fn deserialize_show_without_viewing_state() {
+        let show: Show = ::serde_json::from_str(
+            r#"{"id":20263,"name":"The Orville","language":"English","network":null,
+            "webChannel":null,"status":"Running","runtime":60,"schedule":{"days":[]},
+            "updated":0,"lastWatchedEpisode":[1,2]}"#,
+        )
+        .unwrap();
+        assert_eq!(ViewingState::Watching, show.viewing_state);
+
+        let show: Show = ::serde_json::from_str(
+            &::serde_json::to_string(&Show {
+                viewing_state: ViewingState::PlanToWatch,
+                ..show
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(ViewingState::PlanToWatch, show.viewing_state);
+    }
 
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
+    #[test]
+    fn tag_shows() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+
+        assert!(user_data.add_tags(20263, &["scifi", "Family", "scifi"]));
         assert_eq!(
-            (1, 2),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            vec!["family".to_string(), "scifi".to_string()],
+            user_data.data.subscribed_shows[0].tags
         );
+        assert!(user_data.data.subscribed_shows[0].has_tag("family"));
 
-        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), Some(3)));
-
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
+        assert!(user_data.remove_tags(20263, &["SCIFI", "comedy"]));
         assert_eq!(
-            (1, 2),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            vec!["family".to_string()],
+            user_data.data.subscribed_shows[0].tags
         );
+
+        assert!(!user_data.add_tags(7480, &["scifi"]));
     }
 
     #[test]
-    fn mark_episode_as_unwatched_with_gap() {
+    fn search_notes_and_fields() {
         let mut user_data = load_dev_user_data();
         user_data.add_show(the_orville());
         user_data.add_show(star_trek_discovery());
-        user_data.add_episodes(vec![
-            the_orville_ep1(),
-            the_orville_ep2(),
-            star_trek_discovery_ep1(),
-        ]);
-        assert_eq!(3, user_data.data.unwatched_episodes.len());
+
+        assert!(user_data.add_note(20263, "Wait for season to finish"));
+        assert!(user_data.add_note(20263, "Skip the christmas episode"));
+        assert!(user_data.delete_note(20263, 2));
+        assert!(!user_data.delete_note(20263, 2));
+        assert!(!user_data.delete_note(20263, 0));
+        assert!(user_data.set_field(20263, "Recommended by", Some("Anna")));
+        assert!(user_data.set_field(20263, "Platform", Some("Hulu")));
+        assert!(user_data.set_field(20263, "Platform", None));
+        assert!(!user_data.set_field(20263, "Platform", None));
+        assert!(!user_data.add_note(1, "Not subscribed"));
+
+        let show = &user_data.subscribed_shows()[1];
         assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            vec!["Note: Wait for season to finish".to_string()],
+            show.search_user_data("SEASON")
         );
-
         assert_eq!(
-            Some((1, 2)),
-            user_data.mark_as_watched(20263, Some(1), Some(2))
+            vec!["Recommended by: Anna".to_string()],
+            show.search_user_data("anna")
         );
-
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert!(!user_data.data.unwatched_episodes[1].watched);
-        assert!(user_data.data.unwatched_episodes[2].watched);
         assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            vec!["Name: The Orville".to_string()],
+            show.search_user_data("orville")
         );
+        assert!(user_data.subscribed_shows()[0]
+            .search_user_data("anna")
+            .is_empty());
+    }
 
-        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), Some(2)));
+    #[test]
+    fn digest_cursor_advances() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_episodes(vec![the_orville_ep1(), the_orville_ep2()]);
 
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert!(!user_data.data.unwatched_episodes[1].watched);
-        assert!(user_data.data.unwatched_episodes[2].watched);
+        // Without a previous digest, only episodes that aired after `since` are new
+        let since = Utc.ymd(2017, 9, 12).and_hms(0, 0, 0);
+        let new: Vec<usize> = user_data
+            .new_episodes_since_digest(since)
+            .iter()
+            .map(|episode| episode.episode_id)
+            .collect();
+        assert_eq!(vec![1201556], new);
+
+        user_data.set_last_digest(Utc.ymd(2017, 10, 1).and_hms(0, 0, 0));
+        assert!(user_data.new_episodes_since_digest(since).is_empty());
+
+        // Episodes that are added later are new, even if they aired before the last digest
+        user_data.add_episodes(vec![the_orville_ep3()]);
+        let new: Vec<usize> = user_data
+            .new_episodes_since_digest(since)
+            .iter()
+            .map(|episode| episode.episode_id)
+            .collect();
+        assert_eq!(vec![1201557], new);
+
+        user_data.set_last_digest(Utc.ymd(2017, 10, 2).and_hms(0, 0, 0));
+        assert!(user_data.new_episodes_since_digest(since).is_empty());
+    }
+
+    #[test]
+    fn progress_per_season() {
+        let mut user_data = load_dev_user_data();
+        let mut show = the_orville();
+        show.last_watched_episode = (1, 1);
+        user_data.add_show(show);
+
+        // Episode 1 has been watched, episode 4 hasn't aired yet
+        let mut unaired_episode = the_orville_ep4();
+        unaired_episode.airstamp = Some(Utc::now() + ::chrono::Duration::days(7));
+        user_data.set_episode_counts(&the_orville_episodes());
+        user_data.add_episodes(vec![the_orville_ep2(), the_orville_ep3(), unaired_episode]);
         assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some((1, 3)),
+            user_data.mark_as_watched(20263, Some(1), Some(3))
         );
 
-        assert_eq!(None, user_data.mark_as_watched(20263, Some(1), Some(3)));
-
-        assert!(!user_data.data.unwatched_episodes[0].watched);
-        assert!(!user_data.data.unwatched_episodes[1].watched);
-        assert!(user_data.data.unwatched_episodes[2].watched);
+        let show = user_data.subscribed_shows()[0].clone();
         assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            vec![SeasonProgress {
+                season: 1,
+                watched: 2,
+                aired: 3,
+                total: 4,
+            }],
+            user_data.season_progress(&show)
         );
     }
 
     #[test]
-    fn keep_watched_episodes_after_gap() {
+    fn next_episode_per_show() {
         let mut user_data = load_dev_user_data();
         user_data.add_show(the_orville());
         user_data.add_show(star_trek_discovery());
         user_data.add_episodes(vec![
-            the_orville_ep1(),
             the_orville_ep2(),
             the_orville_ep3(),
             the_orville_ep4(),
             star_trek_discovery_ep1(),
         ]);
-        assert_eq!(5, user_data.data.unwatched_episodes.len());
-        assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
-        );
+        assert_eq!(None, user_data.last_activity(20263));
 
-        assert_eq!(Some((1, 1)), user_data.mark_as_watched(20263, None, None));
+        // Episode 3 is watched after a gap, so episode 2 is still next
         assert_eq!(
-            (1, 1),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some((1, 3)),
+            user_data.mark_as_watched(20263, Some(1), Some(3))
         );
-        assert_eq!(4, user_data.data.unwatched_episodes.len());
 
-        assert_eq!(
-            Some((1, 4)),
-            user_data.mark_as_watched(20263, Some(1), Some(4))
-        );
-        assert_eq!(
-            (1, 1),
-            user_data.data.subscribed_shows[1].last_watched_episode
-        );
-        assert_eq!(4, user_data.data.unwatched_episodes.len());
+        let next_episodes: Vec<(usize, usize, usize)> = user_data
+            .next_episodes()
+            .iter()
+            .map(|&(episode, remaining)| (episode.show_id, episode.number, remaining))
+            .collect();
+        assert_eq!(vec![(7480, 1, 1), (20263, 2, 2)], next_episodes);
+        assert!(user_data.last_activity(20263).is_some());
+        assert_eq!(None, user_data.last_activity(7480));
+    }
+
+    #[test]
+    fn import_watched_episodes() {
+        let mut user_data = load_dev_user_data();
+        let watched_at = Utc.ymd(2026, 3, 2).and_hms(20, 0, 0);
 
+        // Episode 3 is watched after a gap, season 2 hasn't aired
         assert_eq!(
-            Some((1, 2)),
-            user_data.mark_as_watched(20263, Some(1), Some(2))
+            2,
+            user_data.import_watched(
+                the_orville(),
+                the_orville_episodes(),
+                &[((1, 1), None), ((1, 3), Some(watched_at)), ((2, 1), None)]
+            )
         );
+        assert_eq!((1, 1), user_data.subscribed_shows()[0].last_watched_episode);
+        let unwatched: Vec<(usize, bool)> = user_data
+            .unwatched_episodes()
+            .iter()
+            .map(|episode| (episode.number, episode.watched))
+            .collect();
+        assert_eq!(vec![(2, false), (3, true), (4, false)], unwatched);
+        assert_eq!(1, user_data.history().len());
+        assert_eq!(watched_at, user_data.history()[0].watched);
+
+        // Importing again only adds the episodes that haven't been watched yet, the history
+        // stays in time order
+        let earlier = Utc.ymd(2026, 3, 1).and_hms(20, 0, 0);
         assert_eq!(
-            (1, 2),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            2,
+            user_data.import_watched(
+                the_orville(),
+                the_orville_episodes(),
+                &[
+                    ((1, 2), None),
+                    ((1, 3), Some(watched_at)),
+                    ((1, 4), Some(earlier))
+                ]
+            )
         );
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&the_orville_ep3()));
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&the_orville_ep4()));
-        assert!(!user_data.data.unwatched_episodes[1].watched);
-        assert!(user_data.data.unwatched_episodes[2].watched);
-        assert_eq!(3, user_data.data.unwatched_episodes.len());
+        assert_eq!((1, 4), user_data.subscribed_shows()[0].last_watched_episode);
+        assert!(user_data.unwatched_episodes().is_empty());
+        let history: Vec<usize> = user_data
+            .history()
+            .iter()
+            .map(|entry| entry.number)
+            .collect();
+        assert_eq!(vec![4, 3], history);
     }
 
     #[test]
-    fn remove_other_watched_episodes_if_gap_is_eliminated() {
+    fn set_time_of_played_episodes() {
         let mut user_data = load_dev_user_data();
-        user_data.add_show(the_orville());
-        user_data.add_show(star_trek_discovery());
+        let mut show = the_orville();
+        show.last_watched_episode = (1, 1);
+        user_data.add_show(show);
+        user_data.add_episodes(vec![the_orville_ep2(), the_orville_ep3()]);
+
+        user_data.mark_as_watched(20263, Some(1), Some(2));
+        user_data.mark_as_watched(20263, Some(1), Some(3));
+        assert!(!user_data.set_watched_time(20263, 1, 1, Utc::now()));
+
+        // Played before the other episode, which has just been marked
+        let played_at = Utc.ymd(2026, 3, 2).and_hms(20, 0, 0);
+        assert!(user_data.set_watched_time(20263, 1, 3, played_at));
+        let history: Vec<(usize, DateTime<Utc>)> = user_data
+            .history()
+            .iter()
+            .map(|entry| (entry.number, entry.watched))
+            .collect();
+        assert_eq!(2, history.len());
+        assert_eq!((3, played_at), history[0]);
+        assert_eq!(2, history[1].0);
+    }
+
+    #[test]
+    fn merge_show_from_other_machine() {
+        let mut user_data = load_dev_user_data();
+        let mut show = the_orville();
+        show.last_watched_episode = (1, 1);
+        show.tags = vec!["scifi".to_string()];
+        user_data.add_show(show);
         user_data.add_episodes(vec![
-            the_orville_ep1(),
             the_orville_ep2(),
             the_orville_ep3(),
-            star_trek_discovery_ep1(),
+            the_orville_ep4(),
         ]);
-        assert_eq!(4, user_data.data.unwatched_episodes.len());
+
+        // The other machine has watched episode 2 and episode 4 (after a gap)
+        let mut other_show = the_orville();
+        other_show.last_watched_episode = (1, 2);
+        other_show.tags = vec!["comedy".to_string()];
+        let mut other_ep4 = the_orville_ep4();
+        other_ep4.watched = true;
+        user_data.merge_show(other_show, vec![the_orville_ep3(), other_ep4]);
+
+        let show = &user_data.subscribed_shows()[0];
+        assert_eq!((1, 2), show.last_watched_episode);
+        assert_eq!(vec!["comedy", "scifi"], show.tags);
+        let unwatched: Vec<(usize, bool)> = user_data
+            .unwatched_episodes()
+            .iter()
+            .map(|episode| (episode.number, episode.watched))
+            .collect();
+        assert_eq!(vec![(3, false), (4, true)], unwatched);
+
+        // Watching episode 3 on the other machine closes the gap
+        let mut other_ep3 = the_orville_ep3();
+        other_ep3.watched = true;
+        user_data.merge_show(the_orville(), vec![other_ep3]);
+        assert_eq!((1, 4), user_data.subscribed_shows()[0].last_watched_episode);
+        assert!(user_data.unwatched_episodes().is_empty());
+    }
+
+    #[test]
+    fn merge_user_data_with_conflicts() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_show(star_trek_discovery());
+        user_data.add_episodes(vec![the_orville_ep2(), the_orville_ep3()]);
+
+        // The other side has dropped The Orville, watched episode 2 and removed Discovery
+        let mut other = load_dev_user_data();
+        let mut other_show = the_orville();
+        other_show.last_watched_episode = (1, 2);
+        other_show.viewing_state = ViewingState::Dropped(None);
+        other.add_show(other_show);
+        other.add_episodes(vec![the_orville_ep3()]);
+        other
+            .data
+            .history
+            .push(WatchedEpisode::new(&the_orville_ep2(), Utc::now()));
+
+        let mut conflicts = Vec::new();
+        user_data
+            .merge(other, |conflict| {
+                conflicts.push(match conflict {
+                    MergeConflict::OnlyInOther(show) => format!("only in other: {}", show.name),
+                    MergeConflict::OnlyHere(show) => format!("only here: {}", show.name),
+                    MergeConflict::ViewingState(show, state) => {
+                        format!("{}: {} vs. {}", show.name, show.viewing_state, state)
+                    }
+                });
+                Ok(true)
+            })
+            .unwrap();
+
         assert_eq!(
-            (0, 0),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            vec![
+                "The Orville: Watching vs. Dropped",
+                "only here: Star Trek: Discovery",
+            ],
+            conflicts
         );
+        assert_eq!(1, user_data.subscribed_shows().len());
+        let show = &user_data.subscribed_shows()[0];
+        assert_eq!((1, 2), show.last_watched_episode);
+        assert_eq!(ViewingState::Dropped(None), show.viewing_state);
+        assert_eq!(1, user_data.unwatched_episodes().len());
+        assert_eq!(1, user_data.history().len());
+    }
 
-        assert_eq!(Some((1, 1)), user_data.mark_as_watched(20263, None, None));
+    #[test]
+    fn resolve_conflicts_with_base() {
+        let mut base = load_dev_user_data();
+        base.add_show(the_orville());
+
+        let removed = the_orville();
         assert_eq!(
-            (1, 1),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some(true),
+            base.resolve_conflict(&MergeConflict::OnlyHere(&removed))
         );
-        assert_eq!(3, user_data.data.unwatched_episodes.len());
-
         assert_eq!(
-            Some((1, 3)),
-            user_data.mark_as_watched(20263, Some(1), Some(3))
+            Some(false),
+            base.resolve_conflict(&MergeConflict::OnlyInOther(&removed))
         );
+        let added = star_trek_discovery();
         assert_eq!(
-            (1, 1),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some(false),
+            base.resolve_conflict(&MergeConflict::OnlyHere(&added))
+        );
+        assert_eq!(
+            Some(true),
+            base.resolve_conflict(&MergeConflict::OnlyInOther(&added))
         );
-        assert_eq!(3, user_data.data.unwatched_episodes.len());
-        assert!(!user_data.data.unwatched_episodes[1].watched);
-        assert!(user_data.data.unwatched_episodes[2].watched);
 
+        let mut paused = the_orville();
+        paused.viewing_state = ViewingState::Paused;
+        let dropped = ViewingState::Dropped(None);
         assert_eq!(
-            Some((1, 2)),
-            user_data.mark_as_watched(20263, Some(1), Some(2))
+            Some(true),
+            base.resolve_conflict(&MergeConflict::ViewingState(&the_orville(), &dropped))
         );
         assert_eq!(
-            (1, 3),
-            user_data.data.subscribed_shows[1].last_watched_episode
+            Some(false),
+            base.resolve_conflict(&MergeConflict::ViewingState(
+                &paused,
+                &ViewingState::Watching
+            ))
         );
-        assert_eq!(1, user_data.data.unwatched_episodes.len());
-        assert!(user_data
-            .data
-            .unwatched_episodes
-            .contains(&star_trek_discovery_ep1()));
+        assert_eq!(
+            None,
+            base.resolve_conflict(&MergeConflict::ViewingState(&paused, &dropped))
+        );
+    }
+
+    #[test]
+    fn serialize_user_data_stably() {
+        let mut user_data = load_dev_user_data();
+        user_data.add_show(the_orville());
+        user_data.add_show(star_trek_discovery());
+        user_data.add_episodes(vec![the_orville_ep3(), star_trek_discovery_ep1()]);
+
+        let mut other = load_dev_user_data();
+        other.add_episodes(vec![star_trek_discovery_ep1(), the_orville_ep3()]);
+        other.add_show(star_trek_discovery());
+        other.add_show(the_orville());
+
+        // Pretty-printed and independent of the order in which shows have been added
+        let json = user_data.to_json().unwrap();
+        assert!(json.contains("\n  \"subscribed_shows\": [\n"));
+        assert_eq!(json, other.to_json().unwrap());
+
+        let parsed = UserData::from_json(PathBuf::new(), &json).unwrap();
+        assert_eq!(json, parsed.to_json().unwrap());
     }
 }
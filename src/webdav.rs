@@ -0,0 +1,303 @@
+use std::str::FromStr;
+
+use futures::{Future, Stream};
+use hyper::client::HttpConnector;
+use hyper::header::{HeaderValue, AUTHORIZATION, ETAG, IF_MATCH, IF_NONE_MATCH};
+use hyper::{Body, Client, Method, Request, StatusCode, Uri};
+use hyper_tls::HttpsConnector;
+use tokio_core::reactor::Core;
+
+use config::WebDavConfig;
+use errors::*;
+
+/// Result of uploading a file with optimistic concurrency.
+#[derive(Debug, PartialEq)]
+pub enum Upload {
+    /// The file has been stored, with the new ETag (if the server returned one).
+    Stored(Option<String>),
+    /// The file has been changed by someone else in the meantime.
+    Conflict,
+}
+
+/// Client for a single file on a WebDAV server (e.g. Nextcloud).
+pub struct WebDav {
+    core: Core,
+    client: Client<HttpsConnector<HttpConnector>>,
+    uri: Uri,
+    authorization: Option<String>,
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::new();
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
+
+impl WebDav {
+    pub fn new(config: &WebDavConfig) -> Result<Self> {
+        let core = Core::new()?;
+        let client = Client::builder().build(HttpsConnector::new(4)?);
+        let uri = Uri::from_str(&config.url)
+            .chain_err(|| format!("Invalid WebDAV URL [{}]", config.url))?;
+
+        let authorization = config.username.as_ref().map(|username| {
+            let credentials = format!(
+                "{}:{}",
+                username,
+                config
+                    .password
+                    .as_ref()
+                    .map_or("", |password| password.as_str())
+            );
+            format!("Basic {}", base64(credentials.as_bytes()))
+        });
+
+        Ok(Self {
+            core,
+            client,
+            uri,
+            authorization,
+        })
+    }
+
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    fn request(&self, method: Method, body: Body) -> Result<Request<Body>> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(self.uri.clone())
+            .body(body)
+            .chain_err(|| "Unable to create WebDAV request")?;
+
+        if let Some(ref authorization) = self.authorization {
+            request.headers_mut().insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(authorization).chain_err(|| "Invalid WebDAV credentials")?,
+            );
+        }
+
+        Ok(request)
+    }
+
+    /// Downloads the file together with its ETag. Returns `None` if the file doesn't exist.
+    pub fn get(&mut self) -> Result<Option<(String, Option<String>)>> {
+        let request = self.request(Method::GET, Body::empty())?;
+        let uri = self.uri.clone();
+
+        let response = self
+            .client
+            .request(request)
+            .from_err::<Error>()
+            .and_then(move |res| {
+                let status = res.status();
+                let etag = res
+                    .headers()
+                    .get(ETAG)
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(|etag| etag.to_string());
+
+                res.into_body()
+                    .concat2()
+                    .from_err()
+                    .and_then(move |body| match status {
+                        StatusCode::OK => {
+                            Ok(Some((String::from_utf8_lossy(&body).into_owned(), etag)))
+                        }
+                        StatusCode::NOT_FOUND => Ok(None),
+                        status => Err(ErrorKind::HttpError(status, uri).into()),
+                    })
+            });
+
+        self.core
+            .run(response)
+            .chain_err(|| "WebDAV request failed")
+    }
+
+    /// Uploads the file, if it still has the given ETag on the server (or, without an ETag,
+    /// if it doesn't exist yet).
+    pub fn put(&mut self, body: &str, etag: Option<&str>) -> Result<Upload> {
+        let mut request = self.request(Method::PUT, Body::from(body.to_string()))?;
+        let (header, value) = match etag {
+            Some(etag) => (IF_MATCH, etag),
+            None => (IF_NONE_MATCH, "*"),
+        };
+        request.headers_mut().insert(
+            header,
+            HeaderValue::from_str(value).chain_err(|| format!("Invalid ETag [{}]", value))?,
+        );
+        let uri = self.uri.clone();
+
+        let response = self
+            .client
+            .request(request)
+            .from_err::<Error>()
+            .and_then(move |res| match res.status() {
+                StatusCode::OK | StatusCode::CREATED | StatusCode::NO_CONTENT => {
+                    Ok(Upload::Stored(
+                        res.headers()
+                            .get(ETAG)
+                            .and_then(|etag| etag.to_str().ok())
+                            .map(|etag| etag.to_string()),
+                    ))
+                }
+                StatusCode::PRECONDITION_FAILED => Ok(Upload::Conflict),
+                status => Err(ErrorKind::HttpError(status, uri).into()),
+            });
+
+        self.core
+            .run(response)
+            .chain_err(|| "WebDAV request failed")
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use hyper::service::service_fn;
+    use hyper::{Response, Server};
+
+    /// File served by the stand-in, with its content and version (the ETag).
+    pub type StandInFile = Arc<Mutex<Option<(String, usize)>>>;
+
+    /// Content that someone else uploads right before the next upload is processed.
+    pub type Interference = Arc<Mutex<Option<String>>>;
+
+    /// Serves a single file with ETags, like a WebDAV server does.
+    pub fn start_webdav_stand_in() -> (SocketAddr, StandInFile, Interference) {
+        let file: StandInFile = Arc::new(Mutex::new(None));
+        let interference: Interference = Arc::new(Mutex::new(None));
+
+        let (served_file, served_interference) = (Arc::clone(&file), Arc::clone(&interference));
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(move || {
+            let file = Arc::clone(&served_file);
+            let interference = Arc::clone(&served_interference);
+            service_fn(move |request: Request<Body>| {
+                let file = Arc::clone(&file);
+                let interference = Arc::clone(&interference);
+                let (parts, body) = request.into_parts();
+
+                body.concat2().map(move |body| {
+                    let mut file = file.lock().unwrap();
+                    if parts.method == Method::PUT {
+                        if let Some(content) = interference.lock().unwrap().take() {
+                            let version = file.as_ref().map_or(1, |&(_, version)| version + 1);
+                            *file = Some((content, version));
+                        }
+                    }
+                    let header = |name| {
+                        parts
+                            .headers
+                            .get(name)
+                            .map(|value: &HeaderValue| value.to_str().unwrap().to_string())
+                    };
+                    let etag = file
+                        .as_ref()
+                        .map(|&(_, version)| format!("\"{}\"", version));
+
+                    let mut response = Response::builder();
+                    if parts.method == Method::GET {
+                        return match *file {
+                            Some((ref content, _)) => response
+                                .header(ETAG, etag.unwrap())
+                                .body(Body::from(content.clone())),
+                            None => response.status(StatusCode::NOT_FOUND).body(Body::empty()),
+                        }
+                        .unwrap();
+                    }
+
+                    let matches = match (header(IF_MATCH), header(IF_NONE_MATCH)) {
+                        (Some(if_match), _) => Some(if_match) == etag,
+                        (None, Some(_)) => file.is_none(),
+                        (None, None) => true,
+                    };
+                    if !matches {
+                        return response
+                            .status(StatusCode::PRECONDITION_FAILED)
+                            .body(Body::empty())
+                            .unwrap();
+                    }
+
+                    let version = file.as_ref().map_or(1, |&(_, version)| version + 1);
+                    *file = Some((String::from_utf8_lossy(&body).into_owned(), version));
+                    response
+                        .status(StatusCode::CREATED)
+                        .header(ETAG, format!("\"{}\"", version))
+                        .body(Body::empty())
+                        .unwrap()
+                })
+            })
+        });
+
+        let addr = server.local_addr();
+        thread::spawn(move || hyper::rt::run(server.map_err(|e| panic!("{}", e))));
+
+        (addr, file, interference)
+    }
+
+    #[test]
+    fn encode_base64() {
+        assert_eq!("", base64(b""));
+        assert_eq!("bWU=", base64(b"me"));
+        assert_eq!("bWU6c2VjcmV0", base64(b"me:secret"));
+        assert_eq!("bWU6cw==", base64(b"me:s"));
+    }
+
+    #[test]
+    fn upload_with_etags() {
+        let (addr, _, _) = start_webdav_stand_in();
+        let config = WebDavConfig {
+            url: format!("http://{}/bingers/user_data.json", addr),
+            username: Some("me".to_string()),
+            password: Some("secret".to_string()),
+        };
+        let mut webdav = WebDav::new(&config).unwrap();
+
+        assert_eq!(None, webdav.get().unwrap());
+        assert_eq!(
+            Upload::Stored(Some("\"1\"".to_string())),
+            webdav.put("first", None).unwrap()
+        );
+        assert_eq!(Upload::Conflict, webdav.put("again", None).unwrap());
+
+        // Someone else uploads in the meantime
+        let mut other = WebDav::new(&config).unwrap();
+        assert_eq!(
+            Upload::Stored(Some("\"2\"".to_string())),
+            other.put("second", Some("\"1\"")).unwrap()
+        );
+        assert_eq!(
+            Upload::Conflict,
+            webdav.put("third", Some("\"1\"")).unwrap()
+        );
+
+        assert_eq!(
+            Some(("second".to_string(), Some("\"2\"".to_string()))),
+            webdav.get().unwrap()
+        );
+    }
+}